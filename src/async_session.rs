@@ -0,0 +1,500 @@
+//! Async agent session management built on Tokio.
+
+use crate::config::{AgentConfig, AgentKind};
+use crate::error::{Error, ErrorKind, Result};
+use crate::events::{AgentEvent, EventEnvelope, EventSource};
+use crate::probe::CliInfo;
+use crate::process::{
+    attach_prompt, build_command, closing_events, write_settings, CancelHandle, CompletionRecord,
+    Expired, Sequence, TempFile, Timeouts, TurnControl,
+};
+use crate::session::{resume_id, AgentSession};
+use crate::stream::{stderr_event, LineParser};
+use futures_core::Stream;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, timeout, Instant};
+
+/// An async session with an agent CLI.
+///
+/// Mirrors [`AgentSession`] but drives the CLI with `tokio::process` and
+/// exposes events as a [`Stream`]. Events are produced by the same parsers
+/// as the blocking session.
+pub struct AsyncAgentSession {
+    config: AgentConfig,
+    receiver: Option<AsyncReceiver>,
+    stop: Option<oneshot::Sender<()>>,
+    control: Arc<TurnControl>,
+    cancel: CancelHandle,
+    session_id: Option<String>,
+    started: bool,
+    notice: Option<EventEnvelope>,
+    cli_info: Option<CliInfo>,
+}
+
+impl AsyncAgentSession {
+    /// Spawns a new agent session with the given prompt.
+    ///
+    /// Must be awaited within a Tokio runtime. The environment checks,
+    /// including a preflight probe, run on its blocking pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CLI binary is not found, no credentials are
+    /// found, no Tokio runtime is available, or the process fails to spawn.
+    /// Persistent sessions are only supported by [`AgentSession`]. With
+    /// `PromptDelivery::Stdin`, a failed prompt write is reported as an
+    /// `ErrorKind::Input` event, as in [`AgentSession`].
+    pub async fn spawn(config: AgentConfig, prompt: &str) -> Result<Self> {
+        if config.persistent {
+            return Err(Error::UnsupportedOption {
+                cli_kind: "AsyncAgentSession".to_string(),
+                option: "persistent sessions".to_string(),
+            });
+        }
+        let sequence = Sequence::default();
+        let (config, cli_info, notice) = validate_environment(config, None).await?;
+        let notice = notice.map(|e| sequence.envelope(e));
+        let turn = spawn_process(&config, prompt, sequence)?;
+        Ok(Self {
+            config,
+            receiver: Some(turn.receiver),
+            stop: Some(turn.stop),
+            cancel: CancelHandle::new(Arc::clone(&turn.control)),
+            control: turn.control,
+            session_id: None,
+            started: false,
+            notice,
+            cli_info,
+        })
+    }
+
+    /// Returns a stream of events from the agent.
+    ///
+    /// This consumes the receiver, so it can only be called once per turn.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the receiver has already been consumed.
+    pub fn events(&mut self) -> Result<EventStream<'_>> {
+        let receiver = self.receiver.take().ok_or(Error::ReceiverDisconnected)?;
+        Ok(EventStream {
+            receiver,
+            session: self,
+        })
+    }
+
+    /// Returns a stream of events with their sequence number, arrival time
+    /// and source.
+    ///
+    /// Behaves like [`events`](Self::events) otherwise and shares its
+    /// receiver, so only one of the two can be used per turn.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the receiver has already been consumed.
+    pub fn envelopes(&mut self) -> Result<EnvelopeStream<'_>> {
+        self.events().map(EnvelopeStream)
+    }
+
+    /// Sends a follow-up message to continue the conversation.
+    ///
+    /// This spawns a new process that resumes the session captured from the
+    /// previous turn's events.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if multi-turn is not supported, no session ID is
+    /// available, or the process fails to spawn.
+    pub async fn send_input(&mut self, prompt: &str) -> Result<()> {
+        let session_id = resume_id(
+            &self.config,
+            self.session_id.as_ref(),
+            self.started,
+            self.control.is_resumable(),
+        )?;
+        let config = AgentConfig {
+            session_id: Some(session_id),
+            ..self.config.clone()
+        };
+        let sequence = Sequence::default();
+        let (config, cli_info, notice) =
+            validate_environment(config, self.cli_info.clone()).await?;
+        self.cli_info = cli_info;
+        self.notice = notice.map(|e| sequence.envelope(e));
+        let turn = spawn_process(&config, prompt, sequence)?;
+        if let Some(previous) = self.stop.replace(turn.stop) {
+            let _ = previous.send(());
+        }
+        self.cancel.replace(Arc::clone(&turn.control));
+        self.control = turn.control;
+        self.receiver = Some(turn.receiver);
+        Ok(())
+    }
+
+    /// Returns a handle that can cancel the running turn from another task.
+    ///
+    /// The handle stays valid across turns started with `send_input`.
+    #[must_use]
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Returns the session ID if available.
+    #[must_use]
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Returns the agent kind for this session.
+    #[must_use]
+    pub const fn kind(&self) -> &AgentKind {
+        &self.config.kind
+    }
+}
+
+fn current_runtime() -> Result<Handle> {
+    Handle::try_current().map_err(|e| Error::SpawnFailed {
+        source: io::Error::other(e),
+    })
+}
+
+/// Runs [`AgentSession::validate_environment`] on the blocking pool, as a
+/// preflight probe spawns the CLI and waits for it.
+async fn validate_environment(
+    mut config: AgentConfig,
+    mut cli_info: Option<CliInfo>,
+) -> Result<(AgentConfig, Option<CliInfo>, Option<AgentEvent>)> {
+    current_runtime()?
+        .spawn_blocking(move || {
+            let notice = AgentSession::validate_environment(&mut config, &mut cli_info)?;
+            Ok((config, cli_info, notice))
+        })
+        .await
+        .map_err(|e| Error::SpawnFailed {
+            source: io::Error::other(e),
+        })?
+}
+
+fn spawn_process(config: &AgentConfig, prompt: &str, sequence: Sequence) -> Result<SpawnedTurn> {
+    let runtime = current_runtime()?;
+    let settings = write_settings(config)?;
+    let mut std_cmd = build_command(config, prompt, settings.as_ref());
+    let prompt_file = attach_prompt(&mut std_cmd, config, prompt)?;
+    let mut cmd = Command::from(std_cmd);
+    cmd.kill_on_drop(true);
+    let mut child = cmd.spawn().map_err(|e| Error::SpawnFailed { source: e })?;
+    drop(prompt_file);
+    let pid = child.id().ok_or_else(|| Error::SpawnFailed {
+        source: io::Error::other("process exited before it could be tracked"),
+    })?;
+    let control = Arc::new(TurnControl::new(pid, config.cancel_grace_period));
+    let (sender, receiver) = async_channel(config.channel_buffer_size, sequence);
+    let prompt_task = child
+        .stdin
+        .take()
+        .map(|stdin| runtime.spawn(write_prompt(stdin, prompt.to_string(), sender.clone())));
+    let (stop, stop_rx) = oneshot::channel();
+    let turn = Turn {
+        debug: config.debug,
+        timeouts: Timeouts::from_config(config),
+        control: Arc::clone(&control),
+        sender,
+        _settings: settings,
+    };
+    runtime.spawn(turn.drive(child, LineParser::new(config), prompt_task, stop_rx));
+    Ok(SpawnedTurn {
+        stop,
+        control,
+        receiver,
+    })
+}
+
+/// The session's side of a freshly spawned turn.
+struct SpawnedTurn {
+    stop: oneshot::Sender<()>,
+    control: Arc<TurnControl>,
+    receiver: AsyncReceiver,
+}
+
+/// The state of one running turn, owned by its driver task.
+struct Turn {
+    debug: bool,
+    timeouts: Timeouts,
+    control: Arc<TurnControl>,
+    sender: AsyncSender,
+    _settings: Option<TempFile>,
+}
+
+impl Turn {
+    /// Reads the child's output with `parser` until it closes, a timeout
+    /// fires, or the session stops the turn by sending on or dropping `stop`.
+    ///
+    /// The closing events wait for `prompt_task`, so a failed prompt write is
+    /// reported before them.
+    async fn drive(
+        self,
+        mut child: Child,
+        parser: LineParser,
+        prompt_task: Option<JoinHandle<()>>,
+        mut stop: oneshot::Receiver<()>,
+    ) {
+        let stderr_task = child
+            .stderr
+            .take()
+            .map(|err| tokio::spawn(read_stderr(err, self.sender.clone())));
+        let stdout = child.stdout.take();
+        #[cfg(not(unix))]
+        let child = crate::process::SharedChild::new(child, &self.control);
+        let deadline = self.timeouts.total().map(|d| Instant::now() + d);
+        let mut record = CompletionRecord::default();
+        let expired = match stdout {
+            Some(out) => tokio::select! {
+                expired = self.read_stdout(out, parser, &mut record) => expired,
+                () = wait_until(deadline) => self.timeouts.total().map(Expired::Total),
+                _ = &mut stop => {
+                    self.control.kill();
+                    let _ = child.wait().await;
+                    self.control.mark_reaped();
+                    return;
+                }
+            },
+            None => None,
+        };
+        if expired.is_some() {
+            self.control.kill();
+        }
+        let status = child.wait().await.ok();
+        self.control.mark_reaped();
+        if let Some(task) = stderr_task {
+            let _ = task.await;
+        }
+        if let Some(task) = prompt_task {
+            let _ = task.await;
+        }
+        let cancelled = self.control.is_cancelled();
+        let closed_at = SystemTime::now();
+        for event in closing_events(expired, record, status, cancelled) {
+            let sent = self.sender.send(EventSource::Runner, closed_at, event);
+            if sent.await.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn read_stdout<R>(
+        &self,
+        reader: R,
+        mut parser: LineParser,
+        record: &mut CompletionRecord,
+    ) -> Option<Expired>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut lines = BufReader::new(reader).lines();
+        loop {
+            let next = match self.timeouts.idle() {
+                Some(limit) => match timeout(limit, lines.next_line()).await {
+                    Ok(next) => next,
+                    Err(_) => return Some(Expired::Idle(limit)),
+                },
+                None => lines.next_line().await,
+            };
+            match next {
+                Ok(Some(line)) => {
+                    let received_at = SystemTime::now();
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    let events = parser.parse(trimmed);
+                    if !parser.is_resumable() {
+                        self.control.mark_unresumable();
+                    }
+                    for event in events {
+                        let Some(event) = record.observe(event) else {
+                            continue;
+                        };
+                        let sent = self.sender.send(EventSource::Stdout, received_at, event);
+                        if sent.await.is_err() {
+                            return None;
+                        }
+                    }
+                }
+                Ok(None) => return None,
+                Err(e) => {
+                    if self.debug {
+                        let event = AgentEvent::Error {
+                            kind: ErrorKind::Debug,
+                            message: format!("Read error: {e}"),
+                        };
+                        let sent = self
+                            .sender
+                            .send(EventSource::Stdout, SystemTime::now(), event);
+                        let _ = sent.await;
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+async fn wait_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(at) => sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Writes the prompt to the child's stdin and closes it.
+///
+/// Failures are reported in the event stream, since spawning has already
+/// returned by the time the write completes.
+async fn write_prompt(mut stdin: ChildStdin, prompt: String, sender: AsyncSender) {
+    let written = async {
+        stdin.write_all(prompt.as_bytes()).await?;
+        stdin.shutdown().await
+    };
+    if let Err(source) = written.await {
+        let event = AgentEvent::Error {
+            kind: ErrorKind::Input,
+            message: Error::StdinWriteFailed { source }.to_string(),
+        };
+        let _ = sender
+            .send(EventSource::Runner, SystemTime::now(), event)
+            .await;
+    }
+}
+
+async fn read_stderr<R>(reader: R, sender: AsyncSender)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(text)) = lines.next_line().await {
+        if let Some(event) = stderr_event(text) {
+            let sent = sender.send(EventSource::Stderr, SystemTime::now(), event);
+            if sent.await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// A stream of events from an async agent session.
+pub struct EventStream<'a> {
+    receiver: AsyncReceiver,
+    session: &'a mut AsyncAgentSession,
+}
+
+impl EventStream<'_> {
+    fn poll_envelope(&mut self, cx: &mut Context<'_>) -> Poll<Option<EventEnvelope>> {
+        if let Some(notice) = self.session.notice.take() {
+            return Poll::Ready(Some(notice));
+        }
+        let poll = self.receiver.poll_recv(cx);
+        if let Poll::Ready(Some(EventEnvelope {
+            event: AgentEvent::SessionStarted { ref session_id, .. },
+            ..
+        })) = poll
+        {
+            self.session.started = true;
+            if session_id.is_some() {
+                self.session.session_id.clone_from(session_id);
+            }
+        }
+        poll
+    }
+}
+
+impl Stream for EventStream<'_> {
+    type Item = AgentEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .poll_envelope(cx)
+            .map(|envelope| envelope.map(|envelope| envelope.event))
+    }
+}
+
+/// A stream of events from an async agent session with their envelopes.
+pub struct EnvelopeStream<'a>(EventStream<'a>);
+
+impl Stream for EnvelopeStream<'_> {
+    type Item = EventEnvelope;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().0.poll_envelope(cx)
+    }
+}
+
+fn async_channel(buffer_size: usize, sequence: Sequence) -> (AsyncSender, AsyncReceiver) {
+    let (channel, receiver) = if buffer_size == 0 {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (AsyncChannel::Unbounded(tx), AsyncReceiver::Unbounded(rx))
+    } else {
+        let (tx, rx) = mpsc::channel(buffer_size);
+        (AsyncChannel::Bounded(tx), AsyncReceiver::Bounded(rx))
+    };
+    (AsyncSender { channel, sequence }, receiver)
+}
+
+#[derive(Clone)]
+enum AsyncChannel {
+    Bounded(mpsc::Sender<EventEnvelope>),
+    Unbounded(mpsc::UnboundedSender<EventEnvelope>),
+}
+
+#[derive(Clone)]
+struct AsyncSender {
+    channel: AsyncChannel,
+    sequence: Sequence,
+}
+
+impl AsyncSender {
+    /// Sends an event, numbering it once the channel has room so that
+    /// numbers follow channel order.
+    async fn send(
+        &self,
+        source: EventSource,
+        received_at: SystemTime,
+        event: AgentEvent,
+    ) -> std::result::Result<(), ()> {
+        match self.channel {
+            AsyncChannel::Bounded(ref tx) => {
+                let permit = tx.reserve().await.map_err(drop)?;
+                self.sequence
+                    .queue(source, received_at, event, |envelope| permit.send(envelope));
+                Ok(())
+            }
+            AsyncChannel::Unbounded(ref tx) => {
+                self.sequence.queue(source, received_at, event, |envelope| {
+                    tx.send(envelope).map_err(drop)
+                })
+            }
+        }
+    }
+}
+
+enum AsyncReceiver {
+    Bounded(mpsc::Receiver<EventEnvelope>),
+    Unbounded(mpsc::UnboundedReceiver<EventEnvelope>),
+}
+
+impl AsyncReceiver {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<EventEnvelope>> {
+        match self {
+            Self::Bounded(rx) => rx.poll_recv(cx),
+            Self::Unbounded(rx) => rx.poll_recv(cx),
+        }
+    }
+}
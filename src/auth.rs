@@ -0,0 +1,167 @@
+//! Detection of the credentials each CLI can authenticate with.
+//!
+//! Besides API key variables, the built-in CLIs accept subscription logins
+//! cached on disk and cloud provider settings. A session only needs one of
+//! them.
+
+use crate::config::{AgentConfig, AgentKind};
+use crate::error::{Error, ErrorKind, Result};
+use crate::events::AgentEvent;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// How strictly to check for credentials before spawning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthCheck {
+    /// Refuse to spawn without credentials, returning `Error::ApiKeyMissing`.
+    #[default]
+    Require,
+    /// Spawn anyway and start the event stream with an `ErrorKind::Auth`
+    /// error.
+    Warn,
+    /// Do not look for credentials.
+    Skip,
+}
+
+/// A cached login file, optionally only counted when it contains `marker`.
+struct LoginFile {
+    path: PathBuf,
+    marker: Option<&'static str>,
+}
+
+impl LoginFile {
+    const fn new(path: PathBuf) -> Self {
+        Self { path, marker: None }
+    }
+
+    fn exists(&self) -> bool {
+        self.marker.map_or_else(
+            || self.path.is_file(),
+            |marker| {
+                std::fs::read_to_string(&self.path).is_ok_and(|content| content.contains(marker))
+            },
+        )
+    }
+}
+
+/// Checks the configured CLI's credential sources.
+///
+/// Returns the warning to emit before the turn's events when credentials
+/// are missing under `AuthCheck::Warn`.
+///
+/// # Errors
+///
+/// Returns `Error::ApiKeyMissing`, naming the CLI's API key variable, when
+/// credentials are missing under `AuthCheck::Require`.
+pub fn check(config: &AgentConfig) -> Result<Option<AgentEvent>> {
+    if config.auth_check == AuthCheck::Skip {
+        return Ok(None);
+    }
+    let Some(env_var) = config.kind.api_key_env_var() else {
+        return Ok(None);
+    };
+    let found = env_vars(&config.kind)
+        .iter()
+        .chain([&env_var])
+        .any(|key| config.env_var(key).is_some_and(|value| !value.is_empty()))
+        || cloud_provider_enabled(config)
+        || login_files(config).iter().any(LoginFile::exists);
+    if found {
+        return Ok(None);
+    }
+    if config.auth_check == AuthCheck::Require {
+        return Err(Error::ApiKeyMissing {
+            env_var: env_var.to_string(),
+        });
+    }
+    Ok(Some(AgentEvent::Error {
+        kind: ErrorKind::Auth,
+        message: format!(
+            "No credentials found for {}: set {env_var} or log in with the CLI",
+            config.kind.display_name()
+        ),
+    }))
+}
+
+/// Returns the variables besides the API key variable that hold credentials.
+const fn env_vars(kind: &AgentKind) -> &'static [&'static str] {
+    match kind {
+        AgentKind::Claude => &["ANTHROPIC_AUTH_TOKEN", "CLAUDE_CODE_OAUTH_TOKEN"],
+        AgentKind::Codex => &["CODEX_API_KEY"],
+        AgentKind::Gemini => &["GEMINI_API_KEY"],
+        AgentKind::Custom(_) => &[],
+    }
+}
+
+/// Returns the cloud providers a CLI can use instead of its own API, each as
+/// the boolean flag selecting it and the settings it needs.
+const fn cloud_providers(kind: &AgentKind) -> &'static [(&'static str, &'static [&'static str])] {
+    match kind {
+        AgentKind::Claude => &[
+            ("CLAUDE_CODE_USE_BEDROCK", &["AWS_REGION"]),
+            (
+                "CLAUDE_CODE_USE_VERTEX",
+                &["CLOUD_ML_REGION", "ANTHROPIC_VERTEX_PROJECT_ID"],
+            ),
+        ],
+        AgentKind::Gemini => &[
+            ("GOOGLE_GENAI_USE_VERTEXAI", &["GOOGLE_CLOUD_PROJECT"]),
+            (
+                "GOOGLE_GENAI_USE_VERTEXAI",
+                &["GOOGLE_APPLICATION_CREDENTIALS"],
+            ),
+        ],
+        AgentKind::Codex | AgentKind::Custom(_) => &[],
+    }
+}
+
+/// Returns whether a cloud provider is switched on and has its settings.
+///
+/// Flags count as on when set to `1` or `true`, as the CLIs read them, so
+/// `CLAUDE_CODE_USE_BEDROCK=0` does not count.
+fn cloud_provider_enabled(config: &AgentConfig) -> bool {
+    let is_set = |key: &str| config.env_var(key).is_some_and(|value| !value.is_empty());
+    cloud_providers(&config.kind)
+        .iter()
+        .any(|(flag, settings)| {
+            config
+                .env_var(flag)
+                .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+                && settings.iter().all(|key| is_set(key))
+        })
+}
+
+fn login_files(config: &AgentConfig) -> Vec<LoginFile> {
+    let home = config.env_var("HOME").map(PathBuf::from);
+    let dir = |var: &str, default: &str| {
+        config
+            .env_var(var)
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(default)))
+    };
+    match config.kind {
+        AgentKind::Claude => {
+            let mut files: Vec<LoginFile> = dir("CLAUDE_CONFIG_DIR", ".claude")
+                .map(|dir| LoginFile::new(dir.join(".credentials.json")))
+                .into_iter()
+                .collect();
+            if let Some(ref home) = home {
+                files.push(LoginFile {
+                    path: home.join(".claude.json"),
+                    marker: Some("\"oauthAccount\""),
+                });
+            }
+            files
+        }
+        AgentKind::Codex => dir("CODEX_HOME", ".codex")
+            .map(|dir| LoginFile::new(dir.join("auth.json")))
+            .into_iter()
+            .collect(),
+        AgentKind::Gemini => home
+            .map(|home| LoginFile::new(home.join(".gemini").join("oauth_creds.json")))
+            .into_iter()
+            .collect(),
+        AgentKind::Custom(_) => Vec::new(),
+    }
+}
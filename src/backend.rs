@@ -0,0 +1,62 @@
+//! Extension points for driving agent CLIs this crate does not know about.
+//!
+//! Implement [`AgentBackend`] for a CLI that emits JSONL on stdout and pass
+//! it to a session as [`AgentKind::Custom`](crate::AgentKind::Custom).
+
+use crate::config::AgentConfig;
+use crate::error::Result;
+use crate::events::AgentEvent;
+use serde_json::Value;
+use std::fmt::Debug;
+use std::process::Command;
+
+/// Turns the JSON lines of a CLI's stdout into agent events.
+pub trait AgentParser: Send + Sync {
+    /// Parses one JSON line into zero or more events.
+    ///
+    /// Lines that are not valid JSON never reach the parser; they are
+    /// reported as `ErrorKind::UnparsedOutput` errors instead. A parser
+    /// should emit `SessionCompleted` when the CLI prints its own completion
+    /// record so the runner can report it.
+    fn parse(&self, json: &Value) -> Vec<AgentEvent>;
+}
+
+/// Describes how to run an agent CLI and how to read its output.
+pub trait AgentBackend: AgentParser + Debug {
+    /// Returns the binary name, which must be available on PATH.
+    fn binary_name(&self) -> &str;
+
+    /// Returns a human-readable name for the CLI.
+    fn display_name(&self) -> &str {
+        self.binary_name()
+    }
+
+    /// Returns the environment variable that must hold an API key, if any.
+    fn api_key_env_var(&self) -> Option<&str> {
+        None
+    }
+
+    /// Rejects configurations the CLI cannot express.
+    ///
+    /// Called before every spawn. The default accepts everything.
+    ///
+    /// # Errors
+    ///
+    /// Should return `Error::UnsupportedOption` for options such as allowed
+    /// tools, max turns or MCP servers that the CLI has no flag for.
+    fn validate(&self, config: &AgentConfig) -> Result<()> {
+        let _ = config;
+        Ok(())
+    }
+
+    /// Builds the command for one turn.
+    ///
+    /// `config.session_id` is set when a follow-up turn should resume the
+    /// session reported in `SessionStarted`. The prompt should only be passed
+    /// as an argument when `config.prompt_delivery` is `PromptDelivery::Argv`;
+    /// otherwise the runner provides it on stdin. Backends should run
+    /// `config.program()` and pass `config.extra_args` followed by at least
+    /// one fixed flag, so a multi-value flag cannot swallow the prompt. The
+    /// runner sets the working directory, environment and pipes itself.
+    fn build_command(&self, config: &AgentConfig, prompt: &str) -> Command;
+}
@@ -473,7 +473,11 @@ fn detect_log_format(filename: &str) -> Option<LogFormat> {
 
 /// Get the event discriminator value for a given agent and JSON.
 fn get_event_discriminator(agent: &str, json: &Value) -> String {
-    let field = if agent == "codex" { "event" } else { "type" };
+    let field = if agent == "codex" && json.get("event").is_some() {
+        "event"
+    } else {
+        "type"
+    };
 
     json.get(field)
         .and_then(Value::as_str)
@@ -1459,6 +1463,9 @@ mod tests {
         let codex_json: Value = serde_json::from_str(r#"{"event":"session_start"}"#).unwrap();
         assert_eq!(get_event_discriminator("codex", &codex_json), "session_start");
 
+        let codex_exec_json: Value = serde_json::from_str(r#"{"type":"turn.started"}"#).unwrap();
+        assert_eq!(get_event_discriminator("codex", &codex_exec_json), "turn.started");
+
         let gemini_json: Value = serde_json::from_str(r#"{"type":"text"}"#).unwrap();
         assert_eq!(get_event_discriminator("gemini", &gemini_json), "text");
 
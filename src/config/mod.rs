@@ -0,0 +1,536 @@
+//! Configuration for agent CLI sessions.
+
+mod profile;
+
+use crate::auth::AuthCheck;
+use crate::backend::AgentBackend;
+use crate::mcp::McpServer;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The type of agent CLI to use.
+#[derive(Debug, Clone)]
+pub enum AgentKind {
+    /// Claude Code CLI.
+    Claude,
+    /// Codex CLI.
+    Codex,
+    /// Gemini CLI.
+    Gemini,
+    /// A CLI described by a downstream [`AgentBackend`].
+    ///
+    /// Two custom kinds are equal when they share the same backend instance.
+    Custom(Arc<dyn AgentBackend>),
+}
+
+impl AgentKind {
+    /// Returns the binary name for this CLI.
+    #[must_use]
+    pub fn binary_name(&self) -> &str {
+        match self {
+            Self::Claude => "claude",
+            Self::Codex => "codex",
+            Self::Gemini => "gemini",
+            Self::Custom(backend) => backend.binary_name(),
+        }
+    }
+
+    /// Returns the required API key environment variable name, if any.
+    #[must_use]
+    pub fn api_key_env_var(&self) -> Option<&str> {
+        match self {
+            Self::Claude => Some("ANTHROPIC_API_KEY"),
+            Self::Codex => Some("OPENAI_API_KEY"),
+            Self::Gemini => Some("GOOGLE_API_KEY"),
+            Self::Custom(backend) => backend.api_key_env_var(),
+        }
+    }
+
+    /// Returns a human-readable name for this CLI.
+    #[must_use]
+    pub fn display_name(&self) -> &str {
+        match self {
+            Self::Claude => "Claude Code",
+            Self::Codex => "Codex CLI",
+            Self::Gemini => "Gemini CLI",
+            Self::Custom(backend) => backend.display_name(),
+        }
+    }
+}
+
+impl PartialEq for AgentKind {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for AgentKind {}
+
+impl Hash for AgentKind {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        if let Self::Custom(backend) = self {
+            Arc::as_ptr(backend).cast::<()>().hash(state);
+        }
+    }
+}
+
+/// Built-in kinds serialize as `"claude"`, `"codex"` and `"gemini"`; custom
+/// kinds cannot be serialized.
+impl Serialize for AgentKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Self::Custom(backend) = self {
+            return Err(ser::Error::custom(format_args!(
+                "custom agent kind `{}` cannot be serialized",
+                backend.binary_name()
+            )));
+        }
+        serializer.serialize_str(self.binary_name())
+    }
+}
+
+impl<'de> Deserialize<'de> for AgentKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        match name.as_str() {
+            "claude" => Ok(Self::Claude),
+            "codex" => Ok(Self::Codex),
+            "gemini" => Ok(Self::Gemini),
+            other => Err(de::Error::unknown_variant(
+                other,
+                &["claude", "codex", "gemini"],
+            )),
+        }
+    }
+}
+
+/// How the prompt is handed to the CLI process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptDelivery {
+    /// Pass the prompt as a command-line argument.
+    #[default]
+    Argv,
+    /// Write the prompt to the CLI's stdin through a pipe.
+    Stdin,
+    /// Redirect the CLI's stdin from a private temporary file.
+    ///
+    /// The file is removed as soon as the process has been spawned.
+    TempFile,
+}
+
+/// How much the agent may do without asking for approval.
+///
+/// Each mode maps to the CLI's native permission or sandbox flags. Modes a
+/// CLI cannot express are rejected when the session is spawned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionMode {
+    /// Read and plan only; no edits or commands.
+    ReadOnly,
+    /// Edit files without asking; other actions still need approval.
+    AcceptEdits,
+    /// Edit files and run commands inside a sandbox limited to the workspace.
+    WorkspaceWrite,
+    /// Act without approval prompts while keeping the CLI's sandbox.
+    FullAuto,
+    /// Skip all approvals and sandboxing.
+    Bypass,
+}
+
+/// Configuration for an agent session.
+///
+/// Serializes with `snake_case` keys matching the field names. Durations are
+/// written as seconds, and every key except `kind` may be omitted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct AgentConfig {
+    /// The type of agent CLI to use.
+    pub kind: AgentKind,
+    /// Working directory for the CLI process.
+    pub working_dir: Option<PathBuf>,
+    /// Whether to skip permission prompts (dangerous mode).
+    #[serde(default)]
+    pub skip_permissions: bool,
+    /// Permission mode; takes precedence over `skip_permissions`.
+    pub permission_mode: Option<PermissionMode>,
+    /// Optional model override.
+    pub model: Option<String>,
+    /// Session ID for resuming a previous session.
+    pub session_id: Option<String>,
+    /// Whether to enable debug output.
+    #[serde(default)]
+    pub debug: bool,
+    /// Channel buffer size for event streaming (0 = unbounded).
+    #[serde(default = "profile::default_channel_buffer_size")]
+    pub channel_buffer_size: usize,
+    /// Maximum wall-clock time for a single turn.
+    #[serde(default, with = "profile::optional_secs")]
+    pub timeout: Option<Duration>,
+    /// Maximum time to wait for the next stdout line.
+    #[serde(default, with = "profile::optional_secs")]
+    pub idle_timeout: Option<Duration>,
+    /// Time to wait after each cancellation signal before escalating.
+    #[serde(default = "profile::default_grace_period", with = "profile::secs")]
+    pub cancel_grace_period: Duration,
+    /// Whether to keep one CLI process alive across turns.
+    #[serde(default)]
+    pub persistent: bool,
+    /// How the prompt is handed to the CLI process.
+    #[serde(default)]
+    pub prompt_delivery: PromptDelivery,
+    /// Environment overrides; `None` removes the variable.
+    #[serde(default)]
+    pub env: BTreeMap<String, Option<String>>,
+    /// Parent variables to keep when starting from a clean environment.
+    ///
+    /// `None` inherits the parent's full environment.
+    pub env_allowlist: Option<Vec<String>>,
+    /// Additional arguments passed to the CLI before its output format flag.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// Path to the CLI binary, instead of looking it up on PATH.
+    pub binary_path: Option<PathBuf>,
+    /// Instructions appended to the CLI's system prompt.
+    pub append_system_prompt: Option<String>,
+    /// Tools the agent may use without asking; empty allows the defaults.
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Tools the agent must not use.
+    #[serde(default)]
+    pub disallowed_tools: Vec<String>,
+    /// Maximum number of agentic turns per prompt.
+    pub max_turns: Option<u32>,
+    /// MCP servers to attach to the session.
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServer>,
+    /// Whether to probe the CLI before spawning and reject configurations
+    /// its version cannot run.
+    #[serde(default)]
+    pub preflight: bool,
+    /// How strictly to check for credentials before spawning.
+    #[serde(default)]
+    pub auth_check: AuthCheck,
+    /// Whether to stream text, reasoning and tool input as it is generated.
+    #[serde(default)]
+    pub partial_messages: bool,
+}
+
+impl AgentConfig {
+    /// Creates a new configuration for the specified agent kind.
+    #[must_use]
+    pub const fn new(kind: AgentKind) -> Self {
+        Self {
+            kind,
+            working_dir: None,
+            skip_permissions: false,
+            permission_mode: None,
+            model: None,
+            session_id: None,
+            debug: false,
+            channel_buffer_size: profile::default_channel_buffer_size(),
+            timeout: None,
+            idle_timeout: None,
+            cancel_grace_period: profile::default_grace_period(),
+            persistent: false,
+            prompt_delivery: PromptDelivery::Argv,
+            env: BTreeMap::new(),
+            env_allowlist: None,
+            extra_args: Vec::new(),
+            binary_path: None,
+            append_system_prompt: None,
+            allowed_tools: Vec::new(),
+            disallowed_tools: Vec::new(),
+            max_turns: None,
+            mcp_servers: Vec::new(),
+            preflight: false,
+            auth_check: AuthCheck::Require,
+            partial_messages: false,
+        }
+    }
+
+    /// Sets the working directory for the CLI process.
+    #[must_use]
+    pub fn with_working_dir(mut self, dir: PathBuf) -> Self {
+        self.working_dir = Some(dir);
+        self
+    }
+
+    /// Enables dangerous mode to skip permission prompts.
+    #[must_use]
+    pub const fn with_skip_permissions(mut self) -> Self {
+        self.skip_permissions = true;
+        self
+    }
+
+    /// Sets the permission mode, mapped to the CLI's native flags.
+    ///
+    /// Takes precedence over [`with_skip_permissions`](Self::with_skip_permissions).
+    #[must_use]
+    pub const fn with_permission_mode(mut self, mode: PermissionMode) -> Self {
+        self.permission_mode = Some(mode);
+        self
+    }
+
+    /// Sets the model to use for the session.
+    #[must_use]
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sets the session ID for resuming a previous session.
+    #[must_use]
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// Enables debug output.
+    #[must_use]
+    pub const fn with_debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Sets the channel buffer size for event streaming.
+    #[must_use]
+    pub const fn with_channel_buffer_size(mut self, size: usize) -> Self {
+        self.channel_buffer_size = size;
+        self
+    }
+
+    /// Sets the maximum wall-clock time for a turn.
+    ///
+    /// When exceeded, the process is terminated and the stream ends with an
+    /// `ErrorKind::Timeout` error followed by `SessionCompleted`.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum time to wait between stdout lines.
+    ///
+    /// When exceeded, the process is terminated the same way as for
+    /// [`with_timeout`](Self::with_timeout).
+    #[must_use]
+    pub const fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long a cancelled process gets to exit after each signal.
+    ///
+    /// Cancellation sends `SIGINT`, then `SIGTERM`, then `SIGKILL`, waiting
+    /// this long between steps. Defaults to five seconds.
+    #[must_use]
+    pub const fn with_cancel_grace_period(mut self, grace_period: Duration) -> Self {
+        self.cancel_grace_period = grace_period;
+        self
+    }
+
+    /// Keeps one CLI process alive for the whole session.
+    ///
+    /// User messages are written to the CLI's stdin instead of spawning a
+    /// new process per turn, and each turn ends with `TurnCompleted`.
+    /// Timeouts apply to each turn separately, but cancelling a turn or
+    /// hitting a timeout ends the process and with it the session. Only
+    /// supported by Claude Code.
+    #[must_use]
+    pub const fn with_persistent_session(mut self) -> Self {
+        self.persistent = true;
+        self
+    }
+
+    /// Sets how the prompt is handed to the CLI process.
+    ///
+    /// Delivering the prompt on stdin avoids the kernel's argument length
+    /// limit and keeps it out of `ps` output. Persistent sessions always
+    /// write their messages to stdin.
+    #[must_use]
+    pub const fn with_prompt_delivery(mut self, delivery: PromptDelivery) -> Self {
+        self.prompt_delivery = delivery;
+        self
+    }
+
+    /// Sets an environment variable for the CLI process.
+    ///
+    /// Useful for per-tenant API keys or pointing a CLI at a proxy through
+    /// its `*_BASE_URL` variable. Applied after
+    /// [`with_clean_env`](Self::with_clean_env).
+    #[must_use]
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), Some(value.into()));
+        self
+    }
+
+    /// Removes an inherited environment variable from the CLI process.
+    #[must_use]
+    pub fn with_env_remove(mut self, key: impl Into<String>) -> Self {
+        self.env.insert(key.into(), None);
+        self
+    }
+
+    /// Starts the CLI from an empty environment that keeps only the listed
+    /// parent variables.
+    ///
+    /// Keep `PATH` and `HOME` in the list unless the CLI does not need them.
+    #[must_use]
+    pub fn with_clean_env<I, S>(mut self, allowlist: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.env_allowlist = Some(allowlist.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Adds arguments the builders do not know about, such as `--max-turns`
+    /// or Codex's `-c key=value`.
+    ///
+    /// They are passed ahead of the output format flag rather than right
+    /// before the prompt, so a flag taking several values, such as
+    /// `--add-dir`, cannot swallow the prompt.
+    #[must_use]
+    pub fn with_extra_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extra_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Runs the CLI binary at `path` instead of looking it up on PATH.
+    #[must_use]
+    pub fn with_binary_path(mut self, path: PathBuf) -> Self {
+        self.binary_path = Some(path);
+        self
+    }
+
+    /// Appends instructions to the CLI's system prompt.
+    ///
+    /// Not supported by Gemini CLI, which can only replace its system prompt.
+    #[must_use]
+    pub fn with_append_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.append_system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Restricts the agent to the listed tools, using the CLI's tool names.
+    ///
+    /// Not supported by Codex CLI.
+    #[must_use]
+    pub fn with_allowed_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_tools.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Forbids the listed tools, using the CLI's tool names.
+    ///
+    /// Only supported by Claude Code.
+    #[must_use]
+    pub fn with_disallowed_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.disallowed_tools
+            .extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Caps the number of agentic turns per prompt.
+    ///
+    /// Only supported by Claude Code.
+    #[must_use]
+    pub const fn with_max_turns(mut self, max_turns: u32) -> Self {
+        self.max_turns = Some(max_turns);
+        self
+    }
+
+    /// Attaches an MCP server, written into the CLI's own configuration
+    /// format when the process is spawned.
+    ///
+    /// Gemini receives the servers through a system settings file named by
+    /// `GEMINI_CLI_SYSTEM_SETTINGS_PATH`, which replaces any system settings
+    /// file for that process.
+    #[must_use]
+    pub fn with_mcp_server(mut self, server: McpServer) -> Self {
+        self.mcp_servers.push(server);
+        self
+    }
+
+    /// Probes the CLI's version and help text before spawning.
+    ///
+    /// The probe runs once per session and binary, with the configured
+    /// working directory and environment. A configuration that needs a flag
+    /// the installed CLI does not list, such as resuming with a Codex build
+    /// that predates `exec resume`, is then rejected with
+    /// `Error::UnsupportedOption`. See [`AgentKind::probe`].
+    #[must_use]
+    pub const fn with_preflight(mut self) -> Self {
+        self.preflight = true;
+        self
+    }
+
+    /// Sets how strictly credentials are checked before spawning.
+    ///
+    /// Each CLI's API key variables, cached subscription logins and cloud
+    /// provider settings all count as credentials.
+    #[must_use]
+    pub const fn with_auth_check(mut self, check: AuthCheck) -> Self {
+        self.auth_check = check;
+        self
+    }
+
+    /// Streams text, reasoning and tool input as the model generates them.
+    ///
+    /// Claude Code then reports each chunk as a partial `Text` or `Thinking`
+    /// event and each tool call as soon as its input is complete. Streamed
+    /// blocks are not repeated as non-partial events, so callers join the
+    /// chunks themselves; only a thinking block's signature follows, in a
+    /// `Thinking` event without content. Gemini always streams its messages;
+    /// Codex cannot.
+    #[must_use]
+    pub const fn with_partial_messages(mut self) -> Self {
+        self.partial_messages = true;
+        self
+    }
+
+    /// Returns the program to run: the configured binary path, or the CLI's
+    /// binary name to look up on PATH.
+    #[must_use]
+    pub fn program(&self) -> &Path {
+        self.binary_path
+            .as_deref()
+            .unwrap_or_else(|| Path::new(self.kind.binary_name()))
+    }
+
+    /// Returns the value `key` will have in the CLI's environment.
+    pub(crate) fn env_var(&self, key: &str) -> Option<String> {
+        if let Some(value) = self.env.get(key) {
+            return value.clone();
+        }
+        if let Some(ref allowlist) = self.env_allowlist {
+            if !allowlist.iter().any(|allowed| allowed == key) {
+                return None;
+            }
+        }
+        std::env::var(key).ok()
+    }
+}
@@ -0,0 +1,282 @@
+//! Named configuration profiles loaded from JSON or TOML files.
+//!
+//! The top-level keys of a profile file name profiles. Each profile is a
+//! serialized [`AgentConfig`] plus an optional `inherits` key naming the
+//! profile it extends:
+//!
+//! ```toml
+//! [base]
+//! kind = "claude"
+//! model = "sonnet"
+//! env = { ANTHROPIC_API_KEY = "${REVIEW_KEY}" }
+//!
+//! [review]
+//! inherits = "base"
+//! permission_mode = "read_only"
+//! timeout = 600
+//! ```
+
+use super::AgentConfig;
+use crate::error::{Error, Result};
+use serde::{de, Deserialize, Deserializer, Serializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const INHERITS: &str = "inherits";
+
+impl AgentConfig {
+    /// Loads the profile `name` from a profile file.
+    ///
+    /// Files ending in `.toml` are parsed as TOML, which requires the `toml`
+    /// feature; anything else is parsed as JSON. A profile's tables are
+    /// merged key by key over those of the profile it inherits from. `${VAR}`
+    /// in any string of the merged profile is replaced with the environment
+    /// variable `VAR`, and `$$` with a literal `$`, so a variable only needs
+    /// to be set if the key using it is not overridden.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidProfile` if the file cannot be read or parsed,
+    /// a profile is missing or inherits from itself, a referenced variable is
+    /// unset, or a value does not fit its key. The error names the offending
+    /// key as a dotted path such as `review.env.ANTHROPIC_API_KEY`, starting
+    /// with the profile that set it.
+    pub fn from_profile_file(path: impl AsRef<Path>, name: &str) -> Result<Self> {
+        let profiles = read_profiles(path.as_ref())?;
+        let table = resolve(&profiles, name)?;
+        deserialize(name, &table)
+    }
+}
+
+fn invalid(key: Option<String>, message: impl Into<String>) -> Error {
+    Error::InvalidProfile {
+        key,
+        message: message.into(),
+    }
+}
+
+fn read_profiles(path: &Path) -> Result<Map<String, Value>> {
+    let fail = |message: String| invalid(None, format!("{}: {message}", path.display()));
+    let text = std::fs::read_to_string(path).map_err(|e| fail(e.to_string()))?;
+    let document = if path.extension().is_some_and(|ext| ext == "toml") {
+        parse_toml(&text).map_err(fail)?
+    } else {
+        serde_json::from_str(&text).map_err(|e| fail(e.to_string()))?
+    };
+    match document {
+        Value::Object(profiles) => Ok(profiles),
+        _ => Err(fail("expected a table of profiles".to_string())),
+    }
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(text: &str) -> std::result::Result<Value, String> {
+    toml::from_str(text).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_text: &str) -> std::result::Result<Value, String> {
+    Err("TOML profiles require the `toml` feature".to_string())
+}
+
+/// Merges `name` with the profiles it inherits from, then interpolates it.
+fn resolve(profiles: &Map<String, Value>, name: &str) -> Result<Map<String, Value>> {
+    let mut chain: Vec<(String, Map<String, Value>)> = Vec::new();
+    let mut next = Some((name.to_string(), name.to_string()));
+    while let Some((current, key)) = next.take() {
+        if chain.iter().any(|(seen, _)| *seen == current) {
+            return Err(invalid(Some(key), "profiles inherit from each other"));
+        }
+        let Some(Value::Object(table)) = profiles.get(&current) else {
+            return Err(invalid(
+                Some(key),
+                format!("no profile table named `{current}`"),
+            ));
+        };
+        let mut table = table.clone();
+        next = match table.remove(INHERITS) {
+            Some(Value::String(base)) => Some((base, format!("{current}.{INHERITS}"))),
+            Some(_) => {
+                return Err(invalid(
+                    Some(format!("{current}.{INHERITS}")),
+                    "expected a profile name",
+                ))
+            }
+            None => None,
+        };
+        chain.push((current, table));
+    }
+    let mut merged = Map::new();
+    let mut origins = Origins::new();
+    for (profile, table) in chain.into_iter().rev() {
+        merge(&mut merged, table, &profile, "", &mut origins);
+    }
+    for (key, value) in &mut merged {
+        interpolate(value, key, &origins)?;
+    }
+    Ok(merged)
+}
+
+/// The profile that last set each merged key, by path within the profile.
+type Origins = HashMap<String, String>;
+
+fn merge(
+    base: &mut Map<String, Value>,
+    overrides: Map<String, Value>,
+    profile: &str,
+    prefix: &str,
+    origins: &mut Origins,
+) {
+    for (key, value) in overrides {
+        let path = format!("{prefix}{key}");
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(inner)), Value::Object(value)) => {
+                merge(inner, value, profile, &format!("{path}."), origins);
+            }
+            (_, value) => {
+                origins.insert(path, profile.to_string());
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Returns `path` prefixed with the profile that set it or its closest parent.
+fn origin_key(origins: &Origins, path: &str) -> String {
+    let mut parent = path;
+    loop {
+        if let Some(profile) = origins.get(parent) {
+            return format!("{profile}.{path}");
+        }
+        match parent.rfind(['.', '[']) {
+            Some(end) => parent = &parent[..end],
+            None => return path.to_string(),
+        }
+    }
+}
+
+fn interpolate(value: &mut Value, path: &str, origins: &Origins) -> Result<()> {
+    match value {
+        Value::String(text) => *text = substitute(text, &origin_key(origins, path))?,
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                interpolate(item, &format!("{path}[{i}]"), origins)?;
+            }
+        }
+        Value::Object(table) => {
+            for (child, item) in table {
+                interpolate(item, &format!("{path}.{child}"), origins)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn substitute(text: &str, key: &str) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| invalid(Some(key.to_string()), "unterminated `${`"))?;
+            let var = &after[..end];
+            let value = std::env::var(var).map_err(|_| {
+                invalid(
+                    Some(key.to_string()),
+                    format!("environment variable `{var}` is not set"),
+                )
+            })?;
+            out.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Deserializes a merged profile, naming the first key that does not fit.
+fn deserialize(name: &str, table: &Map<String, Value>) -> Result<AgentConfig> {
+    serde_json::from_value(Value::Object(table.clone())).map_err(|e| {
+        let culprit = if table.contains_key("kind") {
+            table
+                .get_key_value("kind")
+                .into_iter()
+                .chain(table)
+                .find(|(key, value)| !fits(table, key, value))
+                .map(|(key, _)| key.as_str())
+        } else {
+            Some("kind")
+        };
+        let key = culprit.map_or_else(|| name.to_string(), |key| format!("{name}.{key}"));
+        invalid(Some(key), e.to_string())
+    })
+}
+
+/// Returns whether `key` deserializes on its own next to the profile's kind.
+fn fits(table: &Map<String, Value>, key: &str, value: &Value) -> bool {
+    let mut probe = Map::new();
+    probe.insert(
+        "kind".to_string(),
+        table.get("kind").cloned().unwrap_or_default(),
+    );
+    probe.insert(key.to_string(), value.clone());
+    serde_json::from_value::<AgentConfig>(Value::Object(probe)).is_ok()
+}
+
+pub const fn default_channel_buffer_size() -> usize {
+    100
+}
+
+pub const fn default_grace_period() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Serializes a duration as fractional seconds.
+pub mod secs {
+    use super::{de, Deserialize, Deserializer, Duration, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(de::Error::custom)
+    }
+}
+
+/// Serializes an optional duration as fractional seconds.
+pub mod optional_secs {
+    use super::{de, Deserialize, Deserializer, Duration, Serializer};
+
+    #[allow(clippy::ref_option)]
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|secs| Duration::try_from_secs_f64(secs).map_err(de::Error::custom))
+            .transpose()
+    }
+}
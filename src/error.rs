@@ -42,6 +42,20 @@ pub enum Error {
         /// The CLI kind that doesn't support multi-turn.
         cli_kind: String,
     },
+    /// The configuration uses an option the CLI or runner cannot express.
+    UnsupportedOption {
+        /// The CLI kind, or the session type, that lacks the option.
+        cli_kind: String,
+        /// The unsupported option.
+        option: String,
+    },
+    /// A profile file could not be loaded.
+    InvalidProfile {
+        /// The dotted path of the offending key, if the error has one.
+        key: Option<String>,
+        /// What is wrong with it.
+        message: String,
+    },
     /// Session resume failed because no session ID is available.
     NoSessionId,
     /// The event receiver was dropped or disconnected.
@@ -72,6 +86,18 @@ impl fmt::Display for Error {
             Self::MultiTurnNotSupported { cli_kind } => {
                 write!(f, "Multi-turn sessions not supported for {cli_kind}")
             }
+            Self::UnsupportedOption { cli_kind, option } => {
+                write!(f, "{cli_kind} does not support {option}")
+            }
+            Self::InvalidProfile {
+                key: Some(key),
+                message,
+            } => {
+                write!(f, "Invalid profile setting {key}: {message}")
+            }
+            Self::InvalidProfile { key: None, message } => {
+                write!(f, "Invalid profile file: {message}")
+            }
             Self::NoSessionId => {
                 write!(f, "Cannot resume session: no session ID available")
             }
@@ -107,6 +133,14 @@ pub enum ErrorKind {
     Debug,
     /// The CLI process terminated unexpectedly.
     ProcessTerminated,
+    /// The turn exceeded its wall-clock or idle timeout.
+    Timeout,
+    /// An error reported by the agent CLI in its event stream.
+    Agent,
+    /// Writing input to the CLI's stdin failed.
+    Input,
+    /// No credentials were found for the CLI.
+    Auth,
 }
 
 impl fmt::Display for ErrorKind {
@@ -117,6 +151,10 @@ impl fmt::Display for ErrorKind {
             Self::JsonParseError => write!(f, "JSON parse error"),
             Self::Debug => write!(f, "debug"),
             Self::ProcessTerminated => write!(f, "process terminated"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::Agent => write!(f, "agent error"),
+            Self::Input => write!(f, "input"),
+            Self::Auth => write!(f, "authentication"),
         }
     }
 }
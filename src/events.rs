@@ -2,6 +2,8 @@
 
 use crate::error::ErrorKind;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 /// An event emitted by an agent CLI during execution.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,16 +21,35 @@ pub enum AgentEvent {
     ToolResult(ToolResult),
     /// Token usage statistics (not guaranteed for all CLIs).
     Usage(Usage),
+    /// What the CLI reported about a finished turn, such as its cost.
+    ///
+    /// Precedes the turn's `SessionCompleted` or `TurnCompleted` when the
+    /// CLI's completion record reports any of its fields.
+    TurnSummary(TurnSummary),
     /// The agent session has started.
     SessionStarted {
         /// The session ID, if available.
         session_id: Option<String>,
+        /// What the CLI reported about its setup.
+        metadata: SessionMetadata,
     },
     /// The agent session has completed.
+    ///
+    /// Emitted exactly once per turn by the runner after the CLI process has
+    /// been reaped, so it reflects how the process really ended.
     SessionCompleted {
-        /// The exit code of the process.
+        /// The exit code of the process, if it exited normally.
         exit_code: Option<i32>,
+        /// The signal that terminated the process, if any (Unix only).
+        signal: Option<i32>,
+        /// Whether the CLI printed its own completion record before exiting.
+        completion_record: bool,
     },
+    /// A turn of a persistent session has finished.
+    ///
+    /// The CLI keeps running and waits for the next message; the session
+    /// still ends with `SessionCompleted` once the process exits.
+    TurnCompleted,
     /// An error occurred during streaming.
     Error {
         /// The kind of error.
@@ -36,8 +57,64 @@ pub enum AgentEvent {
         /// The error message.
         message: String,
     },
-    /// The agent is thinking/processing (no output yet).
-    Thinking,
+    /// Reasoning the agent produced before or between its outputs.
+    Thinking {
+        /// The reasoning text, if the CLI reports it.
+        content: Option<String>,
+        /// Whether this is a partial (streaming) chunk.
+        is_partial: bool,
+        /// Whether the provider withheld the reasoning, leaving only an
+        /// encrypted or signed placeholder.
+        is_redacted: bool,
+        /// The signature Claude attaches to complete reasoning, which the
+        /// Messages API needs to accept the block back in a later request.
+        signature: Option<String>,
+    },
+    /// The turn was cancelled through a `CancelHandle`.
+    ///
+    /// This follows `SessionCompleted` and is always the last event of a
+    /// cancelled turn.
+    Cancelled,
+}
+
+/// An event together with when and where it arrived.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    /// The event's position among those of its CLI process, from zero.
+    ///
+    /// Numbers follow the order in which events are delivered, so they also
+    /// order stdout events relative to stderr events.
+    pub seq: u64,
+    /// When the line that produced the event was read.
+    pub received_at: SystemTime,
+    /// Where the event came from.
+    pub source: EventSource,
+    /// The event itself.
+    pub event: AgentEvent,
+}
+
+/// Where an event came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventSource {
+    /// A line the CLI printed on stdout.
+    Stdout,
+    /// A line the CLI printed on stderr.
+    Stderr,
+    /// The runner itself, such as the closing `SessionCompleted`, a timeout
+    /// or a credentials warning.
+    Runner,
+}
+
+impl AgentEvent {
+    /// Creates a complete, unredacted `Thinking` event.
+    pub(crate) fn thinking(content: Option<&str>) -> Self {
+        Self::Thinking {
+            content: content.filter(|text| !text.is_empty()).map(String::from),
+            is_partial: false,
+            is_redacted: false,
+            signature: None,
+        }
+    }
 }
 
 /// A tool call initiated by the agent.
@@ -49,6 +126,8 @@ pub struct ToolCall {
     pub name: String,
     /// The input arguments as a JSON value.
     pub input: serde_json::Value,
+    /// The MCP server that provides the tool, if it is an MCP tool.
+    pub server: Option<String>,
 }
 
 /// The result of a tool execution.
@@ -62,6 +141,80 @@ pub struct ToolResult {
     pub success: bool,
 }
 
+/// The setup a CLI reports when a session starts.
+///
+/// Fields the CLI does not report are `None` or empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    /// The model the CLI is using.
+    pub model: Option<String>,
+    /// The tools available to the agent.
+    pub tools: Vec<String>,
+    /// The MCP servers the CLI connected to, or tried to.
+    pub mcp_servers: Vec<McpServerStatus>,
+    /// The CLI's own name for its permission mode, such as `acceptEdits`.
+    pub permission_mode: Option<String>,
+    /// The working directory the CLI runs in.
+    pub cwd: Option<PathBuf>,
+    /// The CLI's version.
+    pub cli_version: Option<String>,
+}
+
+/// The state of an MCP server when a session starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpServerStatus {
+    /// The server name.
+    pub name: String,
+    /// The connection status, such as `connected` or `failed`.
+    pub status: Option<String>,
+}
+
+/// Statistics a CLI reports at the end of a turn.
+///
+/// Fields the CLI does not report are `None`. Costs compare by their bit
+/// pattern, which keeps equality reflexive so summaries can be `Eq`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TurnSummary {
+    /// Total cost of the turn in US dollars.
+    pub cost_usd: Option<f64>,
+    /// Wall-clock time the CLI spent on the turn.
+    pub duration: Option<Duration>,
+    /// Time spent waiting for the model API.
+    pub api_duration: Option<Duration>,
+    /// Number of agentic turns taken to answer the prompt.
+    pub num_turns: Option<u32>,
+    /// The final result text.
+    pub result: Option<String>,
+    /// Whether the CLI reported the turn as failed.
+    pub is_error: bool,
+    /// The CLI's classification of the outcome, such as `error_max_turns`.
+    pub subtype: Option<String>,
+    /// Why the model stopped generating, such as `end_turn`.
+    pub stop_reason: Option<String>,
+}
+
+impl PartialEq for TurnSummary {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost_usd.map(f64::to_bits) == other.cost_usd.map(f64::to_bits)
+            && self.duration == other.duration
+            && self.api_duration == other.api_duration
+            && self.num_turns == other.num_turns
+            && self.result == other.result
+            && self.is_error == other.is_error
+            && self.subtype == other.subtype
+            && self.stop_reason == other.stop_reason
+    }
+}
+
+impl Eq for TurnSummary {}
+
+impl TurnSummary {
+    /// Returns whether the CLI reported none of the fields.
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Token usage statistics.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Usage {
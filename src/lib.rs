@@ -7,8 +7,17 @@
 //!
 //! - Unified event model for text, tool calls/results, token usage, and status
 //! - Support for Claude Code, Codex CLI, and Gemini CLI
+//! - Custom backends for other JSONL-emitting CLIs via [`AgentBackend`]
 //! - Per-turn session management with resume capabilities
-//! - Minimal dependencies (`serde`, `serde_json` only)
+//! - Persistent Claude Code sessions that take follow-up messages on stdin
+//! - MCP servers configured once and passed to each CLI in its own format
+//! - Version and feature probes that can reject a configuration before spawning
+//! - Serializable configurations and named profiles loaded from JSON, or TOML
+//!   (`toml`) behind the `toml` feature
+//! - Minimal dependencies: `serde` and `serde_json`, plus `nix` on Unix for
+//!   process-group signals
+//! - Optional async API on Tokio (`tokio`, `futures-core`) behind the `tokio`
+//!   feature
 //!
 //! ## Example
 //!
@@ -26,15 +35,31 @@
 #![deny(missing_docs)]
 #![deny(clippy::all)]
 
+#[cfg(feature = "tokio")]
+mod async_session;
+mod auth;
+mod backend;
 mod config;
 mod error;
 mod events;
+mod mcp;
 mod parsers;
+mod probe;
 mod process;
 mod session;
 mod stream;
 
-pub use config::{AgentConfig, AgentKind};
+#[cfg(feature = "tokio")]
+pub use async_session::AsyncAgentSession;
+pub use auth::AuthCheck;
+pub use backend::{AgentBackend, AgentParser};
+pub use config::{AgentConfig, AgentKind, PermissionMode, PromptDelivery};
 pub use error::{Error, ErrorKind, Result};
-pub use events::{AgentEvent, ToolCall, ToolResult, Usage};
+pub use events::{
+    AgentEvent, EventEnvelope, EventSource, McpServerStatus, SessionMetadata, ToolCall, ToolResult,
+    TurnSummary, Usage,
+};
+pub use mcp::{McpServer, McpTransport};
+pub use probe::{CliFeature, CliInfo};
+pub use process::CancelHandle;
 pub use session::AgentSession;
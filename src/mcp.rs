@@ -0,0 +1,184 @@
+//! Model Context Protocol servers attached to a session.
+//!
+//! Servers are described once and rendered into each CLI's own
+//! configuration format when the process is spawned.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+/// An MCP server the agent may call tools on.
+///
+/// Serializes as its `name` next to either a `stdio` table or a `url`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct McpServer {
+    /// The server name, which also prefixes its tools in the CLI.
+    pub name: String,
+    /// How the CLI reaches the server.
+    #[serde(flatten)]
+    pub transport: McpTransport,
+}
+
+/// How an MCP server is reached.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpTransport {
+    /// A server the CLI starts as a child process and talks to over stdio.
+    Stdio {
+        /// The command to run.
+        command: String,
+        /// Arguments for the command.
+        #[serde(default)]
+        args: Vec<String>,
+        /// Extra environment variables for the server process.
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+    /// A server reached over streamable HTTP.
+    Url(String),
+}
+
+impl McpServer {
+    /// Creates a server that the CLI starts with `command`.
+    #[must_use]
+    pub fn stdio(name: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            transport: McpTransport::Stdio {
+                command: command.into(),
+                args: Vec::new(),
+                env: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Creates a server reached at `url`.
+    #[must_use]
+    pub fn url(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            transport: McpTransport::Url(url.into()),
+        }
+    }
+
+    /// Adds arguments to a stdio server's command; ignored for URL servers.
+    #[must_use]
+    pub fn with_args<I, S>(mut self, new_args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        if let McpTransport::Stdio { ref mut args, .. } = self.transport {
+            args.extend(new_args.into_iter().map(Into::into));
+        }
+        self
+    }
+
+    /// Sets an environment variable for a stdio server; ignored for URL
+    /// servers.
+    ///
+    /// Claude Code and Gemini CLI receive the values in a private file.
+    /// Codex only takes MCP servers on its command line, where other users
+    /// could read them, so it rejects servers with environment variables.
+    #[must_use]
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        if let McpTransport::Stdio { ref mut env, .. } = self.transport {
+            env.insert(key.into(), value.into());
+        }
+        self
+    }
+
+    /// Returns whether the server sets environment variables.
+    pub(crate) fn has_env(&self) -> bool {
+        matches!(self.transport, McpTransport::Stdio { ref env, .. } if !env.is_empty())
+    }
+
+    /// Returns whether the name can be used as a config key by every CLI.
+    pub(crate) fn has_valid_name(&self) -> bool {
+        !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+}
+
+/// Renders the file passed to Claude's `--mcp-config` flag.
+pub fn claude_config(servers: &[McpServer]) -> String {
+    let entries = servers.iter().map(|server| {
+        let entry = match server.transport {
+            McpTransport::Stdio {
+                ref command,
+                ref args,
+                ref env,
+            } => json!({ "type": "stdio", "command": command, "args": args, "env": env }),
+            McpTransport::Url(ref url) => json!({ "type": "http", "url": url }),
+        };
+        (server.name.clone(), entry)
+    });
+    json!({ "mcpServers": entries.collect::<Map<_, _>>() }).to_string()
+}
+
+/// Renders Codex `-c` overrides for the `mcp_servers` table.
+///
+/// JSON strings and arrays of strings are valid TOML, so values are
+/// quoted with `serde_json`. Server environments are rejected before
+/// spawning and never rendered.
+pub fn codex_overrides(servers: &[McpServer]) -> Vec<String> {
+    let mut overrides = Vec::new();
+    for server in servers {
+        let key = format!("mcp_servers.{}", server.name);
+        match server.transport {
+            McpTransport::Stdio {
+                ref command,
+                ref args,
+                ..
+            } => {
+                overrides.push(format!("{key}.command={}", Value::from(command.as_str())));
+                if !args.is_empty() {
+                    overrides.push(format!("{key}.args={}", json!(args)));
+                }
+            }
+            McpTransport::Url(ref url) => {
+                overrides.push(format!("{key}.url={}", Value::from(url.as_str())));
+            }
+        }
+    }
+    overrides
+}
+
+/// Renders a Gemini settings file containing only the `mcpServers` section.
+pub fn gemini_settings(servers: &[McpServer]) -> String {
+    let entries = servers.iter().map(|server| {
+        let entry = match server.transport {
+            McpTransport::Stdio {
+                ref command,
+                ref args,
+                ref env,
+            } => json!({ "command": command, "args": args, "env": env }),
+            McpTransport::Url(ref url) => json!({ "httpUrl": url }),
+        };
+        (server.name.clone(), entry)
+    });
+    json!({ "mcpServers": entries.collect::<Map<_, _>>() }).to_string()
+}
+
+/// Splits a tool name of the form `<prefix><server>__<tool>` into the server
+/// name, if the name has that shape.
+pub fn server_of<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let (server, tool) = name.strip_prefix(prefix)?.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then_some(server)
+}
+
+/// Returns the configured server whose name prefixes a tool name of the
+/// form `<server>__<tool>`.
+pub fn configured_server_of<'a>(name: &str, servers: &'a [String]) -> Option<&'a str> {
+    servers
+        .iter()
+        .find(|server| {
+            name.strip_prefix(server.as_str())
+                .and_then(|rest| rest.strip_prefix("__"))
+                .is_some_and(|tool| !tool.is_empty())
+        })
+        .map(String::as_str)
+}
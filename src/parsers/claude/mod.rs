@@ -0,0 +1,331 @@
+//! Parser for Claude Code JSON streaming output.
+//!
+//! Claude Code emits JSONL events with a "type" field indicating the event kind.
+//! Known event types include:
+//! - "system": The `init` event with the session ID and setup; other
+//!   subtypes such as `compact_boundary` are ignored
+//! - "assistant": Text output with content blocks
+//! - "user": Tool results returned to the model
+//! - "result": Final result with usage statistics
+//! - `stream_event`: A raw API stream event, with `--include-partial-messages`
+//!
+//! Assistant messages may carry `thinking` blocks with the reasoning text and
+//! its signature, and `redacted_thinking` blocks whose reasoning is encrypted.
+//!
+//! MCP tools are named `mcp__<server>__<tool>`.
+
+mod partial;
+
+pub use partial::PartialMessages;
+
+use crate::events::{
+    AgentEvent, McpServerStatus, SessionMetadata, ToolCall, ToolResult, TurnSummary, Usage,
+};
+use crate::mcp;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Parses a Claude Code JSON event into agent events.
+///
+/// `partial` carries the state of streamed content blocks between lines.
+pub fn parse(json: &Value, partial: &mut PartialMessages) -> Vec<AgentEvent> {
+    let mut events = Vec::new();
+    let event_type = json.get("type").and_then(Value::as_str).unwrap_or("");
+    match event_type {
+        "system" => parse_system(json, &mut events),
+        "assistant" => {
+            parse_assistant(json, &mut events);
+            events = events
+                .into_iter()
+                .filter_map(|event| partial.unstreamed(event))
+                .collect();
+        }
+        "stream_event" => {
+            if let Some(event) = json.get("event") {
+                partial.parse(event, &mut events);
+            }
+        }
+        "user" => parse_user(json, &mut events),
+        "result" => parse_result(json, &mut events),
+        "tool_use" => parse_tool_use(json, &mut events),
+        "tool_result" => parse_tool_result(json, &mut events),
+        "thinking" | "redacted_thinking" => parse_thinking(json, &mut events),
+        _ => {
+            if let Some(text) = extract_text_content(json) {
+                events.push(AgentEvent::Text {
+                    content: text,
+                    is_partial: false,
+                });
+            }
+        }
+    }
+    events
+}
+
+fn parse_system(json: &Value, events: &mut Vec<AgentEvent>) {
+    let subtype = json.get("subtype").and_then(Value::as_str);
+    if subtype.is_some_and(|subtype| subtype != "init") {
+        return;
+    }
+    let session_id = json
+        .get("session_id")
+        .or_else(|| json.get("sessionId"))
+        .and_then(Value::as_str)
+        .map(String::from);
+    let metadata = parse_metadata(json);
+    events.push(AgentEvent::SessionStarted {
+        session_id,
+        metadata,
+    });
+}
+
+/// Reads the setup reported by the `init` system event.
+fn parse_metadata(json: &Value) -> SessionMetadata {
+    let text = |key: &str| json.get(key).and_then(Value::as_str).map(String::from);
+    let list = |key: &str| {
+        json.get(key)
+            .and_then(Value::as_array)
+            .map_or(&[][..], Vec::as_slice)
+    };
+    SessionMetadata {
+        model: text("model"),
+        tools: list("tools")
+            .iter()
+            .filter_map(|tool| tool.as_str().map(String::from))
+            .collect(),
+        mcp_servers: list("mcp_servers")
+            .iter()
+            .filter_map(|server| {
+                Some(McpServerStatus {
+                    name: server.get("name")?.as_str()?.to_string(),
+                    status: server
+                        .get("status")
+                        .and_then(Value::as_str)
+                        .map(String::from),
+                })
+            })
+            .collect(),
+        permission_mode: text("permissionMode"),
+        cwd: text("cwd").map(PathBuf::from),
+        cli_version: text("claude_code_version"),
+    }
+}
+
+fn parse_assistant(json: &Value, events: &mut Vec<AgentEvent>) {
+    if let Some(message) = json.get("message") {
+        if let Some(content) = message.get("content") {
+            parse_content_blocks(content, events);
+        }
+    } else if let Some(content) = json.get("content") {
+        parse_content_blocks(content, events);
+    }
+}
+
+fn parse_user(json: &Value, events: &mut Vec<AgentEvent>) {
+    let content = json
+        .get("message")
+        .and_then(|message| message.get("content"))
+        .or_else(|| json.get("content"));
+    let Some(blocks) = content.and_then(Value::as_array) else {
+        return;
+    };
+    for block in blocks {
+        if block.get("type").and_then(Value::as_str) == Some("tool_result") {
+            if let Some(result) = parse_tool_result_from_block(block) {
+                events.push(AgentEvent::ToolResult(result));
+            }
+        }
+    }
+}
+
+fn parse_content_blocks(content: &Value, events: &mut Vec<AgentEvent>) {
+    if let Some(blocks) = content.as_array() {
+        for block in blocks {
+            parse_content_block(block, events);
+        }
+    }
+}
+
+fn parse_content_block(block: &Value, events: &mut Vec<AgentEvent>) {
+    let block_type = block.get("type").and_then(Value::as_str).unwrap_or("");
+    match block_type {
+        "text" => {
+            if let Some(text) = block.get("text").and_then(Value::as_str) {
+                events.push(AgentEvent::Text {
+                    content: text.to_string(),
+                    is_partial: false,
+                });
+            }
+        }
+        "tool_use" => {
+            if let Some(call) = parse_tool_call_from_block(block) {
+                events.push(AgentEvent::ToolCall(call));
+            }
+        }
+        "tool_result" => {
+            if let Some(result) = parse_tool_result_from_block(block) {
+                events.push(AgentEvent::ToolResult(result));
+            }
+        }
+        "thinking" | "redacted_thinking" => parse_thinking(block, events),
+        _ => {}
+    }
+}
+
+fn parse_thinking(block: &Value, events: &mut Vec<AgentEvent>) {
+    if block.get("type").and_then(Value::as_str) == Some("redacted_thinking") {
+        events.push(AgentEvent::Thinking {
+            content: None,
+            is_partial: false,
+            is_redacted: true,
+            signature: None,
+        });
+        return;
+    }
+    let text = block
+        .get("thinking")
+        .or_else(|| block.get("text"))
+        .or_else(|| block.get("content"))
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty());
+    let signature = block.get("signature").and_then(Value::as_str);
+    events.push(AgentEvent::Thinking {
+        content: text.map(String::from),
+        is_partial: false,
+        is_redacted: false,
+        signature: signature.filter(|sig| !sig.is_empty()).map(String::from),
+    });
+}
+
+fn parse_tool_use(json: &Value, events: &mut Vec<AgentEvent>) {
+    if let Some(call) = parse_tool_call_from_block(json) {
+        events.push(AgentEvent::ToolCall(call));
+    }
+}
+
+fn parse_tool_result(json: &Value, events: &mut Vec<AgentEvent>) {
+    if let Some(result) = parse_tool_result_from_block(json) {
+        events.push(AgentEvent::ToolResult(result));
+    }
+}
+
+pub(super) fn parse_tool_call_from_block(block: &Value) -> Option<ToolCall> {
+    let id = block.get("id").and_then(Value::as_str)?.to_string();
+    let name = block.get("name").and_then(Value::as_str)?.to_string();
+    let input = block.get("input").cloned().unwrap_or(Value::Null);
+    let server = mcp::server_of(&name, "mcp__").map(String::from);
+    Some(ToolCall {
+        id,
+        name,
+        input,
+        server,
+    })
+}
+
+fn parse_tool_result_from_block(block: &Value) -> Option<ToolResult> {
+    let tool_call_id = block
+        .get("tool_use_id")
+        .or_else(|| block.get("tool_call_id"))
+        .and_then(Value::as_str)?
+        .to_string();
+    let output = match block.get("content").or_else(|| block.get("output")) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(tool_result_part_text)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    let is_error = block.get("is_error").and_then(Value::as_bool).unwrap_or(false);
+    Some(ToolResult {
+        tool_call_id,
+        output,
+        success: !is_error,
+    })
+}
+
+fn tool_result_part_text(part: &Value) -> Option<String> {
+    match part.get("type").and_then(Value::as_str)? {
+        "text" => part.get("text").and_then(Value::as_str).map(String::from),
+        "image" => {
+            let media_type = part
+                .get("source")
+                .and_then(|source| source.get("media_type"))
+                .and_then(Value::as_str)
+                .unwrap_or("unknown");
+            Some(format!("[image: {media_type}]"))
+        }
+        _ => None,
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn parse_result(json: &Value, events: &mut Vec<AgentEvent>) {
+    if let Some(usage) = parse_usage(json) {
+        events.push(AgentEvent::Usage(usage));
+    }
+    let summary = parse_summary(json);
+    if !summary.is_empty() {
+        events.push(AgentEvent::TurnSummary(summary));
+    }
+    let exit_code = json.get("exit_code").and_then(Value::as_i64).map(|c| c as i32);
+    events.push(AgentEvent::SessionCompleted {
+        exit_code,
+        signal: None,
+        completion_record: true,
+    });
+}
+
+fn parse_summary(json: &Value) -> TurnSummary {
+    let text = |key: &str| json.get(key).and_then(Value::as_str).map(String::from);
+    let millis = |key: &str| {
+        json.get(key)
+            .and_then(Value::as_u64)
+            .map(Duration::from_millis)
+    };
+    TurnSummary {
+        cost_usd: json
+            .get("total_cost_usd")
+            .or_else(|| json.get("cost_usd"))
+            .and_then(Value::as_f64),
+        duration: millis("duration_ms"),
+        api_duration: millis("duration_api_ms"),
+        num_turns: json
+            .get("num_turns")
+            .and_then(Value::as_u64)
+            .and_then(|turns| u32::try_from(turns).ok()),
+        result: text("result"),
+        is_error: json
+            .get("is_error")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        subtype: text("subtype"),
+        stop_reason: text("stop_reason"),
+    }
+}
+
+fn parse_usage(json: &Value) -> Option<Usage> {
+    let usage = json.get("usage")?;
+    let input_tokens = usage.get("input_tokens").and_then(Value::as_u64).unwrap_or(0);
+    let output_tokens = usage.get("output_tokens").and_then(Value::as_u64).unwrap_or(0);
+    let cache_read = usage.get("cache_read_input_tokens").and_then(Value::as_u64);
+    let cache_write = usage.get("cache_creation_input_tokens").and_then(Value::as_u64);
+    Some(Usage {
+        input_tokens,
+        output_tokens,
+        cache_read_tokens: cache_read,
+        cache_write_tokens: cache_write,
+    })
+}
+
+fn extract_text_content(json: &Value) -> Option<String> {
+    if let Some(text) = json.get("text").and_then(Value::as_str) {
+        return Some(text.to_string());
+    }
+    if let Some(content) = json.get("content").and_then(Value::as_str) {
+        return Some(content.to_string());
+    }
+    None
+}
@@ -0,0 +1,172 @@
+//! Assembly of the `stream_event` lines Claude Code emits with
+//! `--include-partial-messages`.
+//!
+//! Each line wraps a raw Messages API stream event. Text and thinking deltas
+//! are reported as they arrive. A tool call's input arrives as JSON fragments
+//! and is reported once its content block stops. The blocks of the complete
+//! assistant message that follows are then skipped, except for the signature
+//! of a thinking block, which only the complete block carries.
+
+use super::parse_tool_call_from_block;
+use crate::events::{AgentEvent, ToolCall};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Content blocks being streamed, keyed by their index in the message.
+#[derive(Debug, Default)]
+pub struct PartialMessages {
+    tool_uses: HashMap<u64, ToolUse>,
+    blocks: HashMap<u64, Streamed>,
+    streamed_blocks: Vec<Streamed>,
+    streamed_calls: HashSet<String>,
+}
+
+/// The content of a text or thinking block reported from its deltas.
+#[derive(Debug, PartialEq, Eq)]
+enum Streamed {
+    Text(String),
+    Thinking(String),
+}
+
+/// A `tool_use` block whose input is still arriving.
+#[derive(Debug)]
+struct ToolUse {
+    block: Value,
+    input_json: String,
+}
+
+impl PartialMessages {
+    /// Parses one stream event into partial agent events.
+    pub fn parse(&mut self, event: &Value, events: &mut Vec<AgentEvent>) {
+        let index = event.get("index").and_then(Value::as_u64).unwrap_or(0);
+        match event.get("type").and_then(Value::as_str).unwrap_or("") {
+            "message_start" => {
+                self.tool_uses.clear();
+                self.blocks.clear();
+                self.streamed_blocks.clear();
+                self.streamed_calls.clear();
+            }
+            "content_block_start" => {
+                let block = event.get("content_block").unwrap_or(&Value::Null);
+                if block.get("type").and_then(Value::as_str) == Some("tool_use") {
+                    let tool_use = ToolUse {
+                        block: block.clone(),
+                        input_json: String::new(),
+                    };
+                    self.tool_uses.insert(index, tool_use);
+                }
+            }
+            "content_block_delta" => {
+                if let Some(delta) = event.get("delta") {
+                    self.parse_delta(index, delta, events);
+                }
+            }
+            "content_block_stop" => {
+                if let Some(block) = self.blocks.remove(&index) {
+                    self.streamed_blocks.push(block);
+                }
+                if let Some(call) = self.finish_tool_use(index) {
+                    self.streamed_calls.insert(call.id.clone());
+                    events.push(AgentEvent::ToolCall(call));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn parse_delta(&mut self, index: u64, delta: &Value, events: &mut Vec<AgentEvent>) {
+        let text = |key: &str| delta.get(key).and_then(Value::as_str).unwrap_or("");
+        match delta.get("type").and_then(Value::as_str).unwrap_or("") {
+            "text_delta" if !text("text").is_empty() => {
+                self.append(index, Streamed::Text, text("text"));
+                events.push(AgentEvent::Text {
+                    content: text("text").to_string(),
+                    is_partial: true,
+                });
+            }
+            "thinking_delta" if !text("thinking").is_empty() => {
+                self.append(index, Streamed::Thinking, text("thinking"));
+                events.push(AgentEvent::Thinking {
+                    content: Some(text("thinking").to_string()),
+                    is_partial: true,
+                    is_redacted: false,
+                    signature: None,
+                });
+            }
+            "input_json_delta" => {
+                if let Some(tool_use) = self.tool_uses.get_mut(&index) {
+                    tool_use.input_json.push_str(text("partial_json"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds a delta to the content streamed for the block at `index`.
+    fn append(&mut self, index: u64, kind: fn(String) -> Streamed, delta: &str) {
+        let block = self
+            .blocks
+            .entry(index)
+            .or_insert_with(|| kind(String::new()));
+        match block {
+            Streamed::Text(content) | Streamed::Thinking(content) => content.push_str(delta),
+        }
+    }
+
+    /// Completes the tool call at `index` from its accumulated input.
+    fn finish_tool_use(&mut self, index: u64) -> Option<ToolCall> {
+        let ToolUse {
+            mut block,
+            input_json,
+        } = self.tool_uses.remove(&index)?;
+        if !input_json.trim().is_empty() {
+            let input = serde_json::from_str(&input_json).unwrap_or(Value::String(input_json));
+            block["input"] = input;
+        }
+        parse_tool_call_from_block(&block)
+    }
+
+    /// Returns `event` from a complete assistant message unless it was
+    /// already reported from its stream events.
+    ///
+    /// A streamed thinking block with a signature is reduced to a `Thinking`
+    /// event carrying only the signature.
+    pub fn unstreamed(&mut self, event: AgentEvent) -> Option<AgentEvent> {
+        let streamed = match event {
+            AgentEvent::ToolCall(ref call) => {
+                return (!self.streamed_calls.remove(&call.id)).then_some(event)
+            }
+            AgentEvent::Text {
+                ref content,
+                is_partial: false,
+            } => Streamed::Text(content.clone()),
+            AgentEvent::Thinking {
+                content: Some(ref content),
+                is_partial: false,
+                ..
+            } => Streamed::Thinking(content.clone()),
+            _ => return Some(event),
+        };
+        let Some(position) = self
+            .streamed_blocks
+            .iter()
+            .position(|block| *block == streamed)
+        else {
+            return Some(event);
+        };
+        self.streamed_blocks.remove(position);
+        match event {
+            AgentEvent::Thinking {
+                signature: Some(signature),
+                is_redacted,
+                ..
+            } => Some(AgentEvent::Thinking {
+                content: None,
+                is_partial: false,
+                is_redacted,
+                signature: Some(signature),
+            }),
+            _ => None,
+        }
+    }
+}
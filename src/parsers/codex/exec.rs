@@ -0,0 +1,237 @@
+//! Parser for the current Codex `exec --json` event protocol.
+//!
+//! Events carry a `type` field. Known event types include:
+//! - `thread.started`: Thread initialization with the resumable `thread_id`
+//! - `turn.started`: The agent started working on the prompt
+//! - `item.started` / `item.updated` / `item.completed`: Progress of a thread
+//!   item such as `agent_message`, `reasoning`, `command_execution`,
+//!   `file_change`, `mcp_tool_call`, `web_search`, `todo_list` or `error`
+//! - `turn.completed` / `turn.failed`: Turn completion with usage or error
+//! - `error`: A fatal stream error
+
+use crate::error::ErrorKind;
+use crate::events::{AgentEvent, SessionMetadata, ToolCall, ToolResult, TurnSummary, Usage};
+use serde_json::{json, Value};
+
+/// Parses a Codex `exec --json` event into agent events.
+pub fn parse(json: &Value) -> Vec<AgentEvent> {
+    let mut events = Vec::new();
+    let event_type = json.get("type").and_then(Value::as_str).unwrap_or("");
+    match event_type {
+        "thread.started" => {
+            let session_id = json
+                .get("thread_id")
+                .and_then(Value::as_str)
+                .map(String::from);
+            events.push(AgentEvent::SessionStarted {
+                session_id,
+                metadata: SessionMetadata::default(),
+            });
+        }
+        "item.started" | "item.updated" | "item.completed" => {
+            if let Some(item) = json.get("item") {
+                parse_item(event_type, item, &mut events);
+            }
+        }
+        "turn.completed" => parse_turn_completed(json, &mut events),
+        "turn.failed" => {
+            push_error(json.get("error"), &mut events);
+            events.push(AgentEvent::TurnSummary(TurnSummary {
+                is_error: true,
+                ..TurnSummary::default()
+            }));
+            events.push(completion());
+        }
+        "error" => push_error(Some(json), &mut events),
+        _ => {}
+    }
+    events
+}
+
+fn parse_item(event_type: &str, item: &Value, events: &mut Vec<AgentEvent>) {
+    let item_type = item.get("type").and_then(Value::as_str).unwrap_or("");
+    let completed = event_type == "item.completed";
+    match (item_type, event_type) {
+        ("agent_message", "item.completed") => {
+            if let Some(text) = item.get("text").and_then(Value::as_str) {
+                events.push(AgentEvent::Text {
+                    content: text.to_string(),
+                    is_partial: false,
+                });
+            }
+        }
+        ("reasoning", "item.completed") => {
+            events.push(AgentEvent::thinking(
+                item.get("text").and_then(Value::as_str),
+            ));
+        }
+        // A todo list is reported once; its result lists the final state of
+        // the items its updates tick off.
+        ("command_execution" | "mcp_tool_call" | "todo_list", "item.started") => {
+            push_tool_call(item_type, item, events);
+        }
+        ("file_change" | "web_search", "item.completed") => {
+            push_tool_call(item_type, item, events);
+            push_tool_result(item_type, item, events);
+        }
+        ("command_execution" | "mcp_tool_call" | "todo_list", "item.completed") => {
+            push_tool_result(item_type, item, events);
+        }
+        ("error", _) if completed => push_error(Some(item), events),
+        _ => {}
+    }
+}
+
+fn push_tool_call(item_type: &str, item: &Value, events: &mut Vec<AgentEvent>) {
+    let Some(id) = item.get("id").and_then(Value::as_str) else {
+        return;
+    };
+    let field = |key: &str| item.get(key).cloned().unwrap_or(Value::Null);
+    let (name, input) = match item_type {
+        "command_execution" => (
+            item_type.to_string(),
+            json!({ "command": field("command") }),
+        ),
+        "mcp_tool_call" => (
+            item.get("tool")
+                .and_then(Value::as_str)
+                .unwrap_or(item_type)
+                .to_string(),
+            field("arguments"),
+        ),
+        "file_change" => (
+            item_type.to_string(),
+            json!({ "changes": field("changes") }),
+        ),
+        "web_search" => (item_type.to_string(), json!({ "query": field("query") })),
+        "todo_list" => (item_type.to_string(), json!({ "items": field("items") })),
+        _ => return,
+    };
+    let server = (item_type == "mcp_tool_call")
+        .then(|| item.get("server").and_then(Value::as_str).map(String::from))
+        .flatten();
+    events.push(AgentEvent::ToolCall(ToolCall {
+        id: id.to_string(),
+        name,
+        input,
+        server,
+    }));
+}
+
+fn push_tool_result(item_type: &str, item: &Value, events: &mut Vec<AgentEvent>) {
+    let Some(id) = item.get("id").and_then(Value::as_str) else {
+        return;
+    };
+    let status = item.get("status").and_then(Value::as_str);
+    let output = match item_type {
+        "command_execution" => item
+            .get("aggregated_output")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string(),
+        "mcp_tool_call" => mcp_output(item),
+        "file_change" => file_change_summary(item),
+        "todo_list" => todo_summary(item),
+        _ => String::new(),
+    };
+    let exit_ok = item
+        .get("exit_code")
+        .and_then(Value::as_i64)
+        .is_none_or(|code| code == 0);
+    let success = exit_ok && !matches!(status, Some("failed" | "declined"));
+    events.push(AgentEvent::ToolResult(ToolResult {
+        tool_call_id: id.to_string(),
+        output,
+        success,
+    }));
+}
+
+fn mcp_output(item: &Value) -> String {
+    if let Some(message) = item
+        .get("error")
+        .and_then(|error| error.get("message"))
+        .and_then(Value::as_str)
+    {
+        return message.to_string();
+    }
+    item.get("result")
+        .and_then(|result| result.get("content"))
+        .and_then(Value::as_array)
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+fn file_change_summary(item: &Value) -> String {
+    item.get("changes")
+        .and_then(Value::as_array)
+        .map(|changes| {
+            changes
+                .iter()
+                .filter_map(|change| {
+                    let path = change.get("path").and_then(Value::as_str)?;
+                    let kind = change
+                        .get("kind")
+                        .and_then(Value::as_str)
+                        .unwrap_or("update");
+                    Some(format!("{kind} {path}"))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+fn todo_summary(item: &Value) -> String {
+    item.get("items")
+        .and_then(Value::as_array)
+        .map(|todos| {
+            todos
+                .iter()
+                .filter_map(|todo| {
+                    let text = todo.get("text").and_then(Value::as_str)?;
+                    let done = todo.get("completed").and_then(Value::as_bool) == Some(true);
+                    Some(format!("[{}] {text}", if done { 'x' } else { ' ' }))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+fn parse_turn_completed(json: &Value, events: &mut Vec<AgentEvent>) {
+    if let Some(usage) = json.get("usage") {
+        let tokens = |key: &str| usage.get(key).and_then(Value::as_u64);
+        events.push(AgentEvent::Usage(Usage {
+            input_tokens: tokens("input_tokens").unwrap_or(0),
+            output_tokens: tokens("output_tokens").unwrap_or(0),
+            cache_read_tokens: tokens("cached_input_tokens"),
+            cache_write_tokens: None,
+        }));
+    }
+    events.push(completion());
+}
+
+fn push_error(error: Option<&Value>, events: &mut Vec<AgentEvent>) {
+    let message = error
+        .and_then(|error| error.get("message"))
+        .and_then(Value::as_str)
+        .unwrap_or("unknown error");
+    events.push(AgentEvent::Error {
+        kind: ErrorKind::Agent,
+        message: message.to_string(),
+    });
+}
+
+const fn completion() -> AgentEvent {
+    AgentEvent::SessionCompleted {
+        exit_code: None,
+        signal: None,
+        completion_record: true,
+    }
+}
@@ -1,16 +1,20 @@
-//! Parser for Codex CLI JSON streaming output.
+//! Parser for the legacy Codex CLI event protocol.
 //!
-//! Codex CLI emits JSONL events with an `event` field indicating the event kind.
+//! Older Codex CLI versions emit JSONL events with an `event` field indicating
+//! the event kind.
 //! Known event types include:
 //! - `session_start`: Session initialization
 //! - `message`: Agent messages (text, tool calls, etc.)
 //! - `exec_result`: Tool execution results
 //! - `session_end`: Session completion
+//!
+//! These versions cannot resume sessions. The session ID is still reported,
+//! but follow-up turns fail with `Error::MultiTurnNotSupported`.
 
-use crate::events::{AgentEvent, ToolCall, ToolResult, Usage};
+use crate::events::{AgentEvent, SessionMetadata, ToolCall, ToolResult, Usage};
 use serde_json::Value;
 
-/// Parses a Codex CLI JSON event into agent events.
+/// Parses a legacy Codex CLI JSON event into agent events.
 pub fn parse(json: &Value) -> Vec<AgentEvent> {
     let mut events = Vec::new();
     let event_type = json.get("event").and_then(Value::as_str).unwrap_or("");
@@ -19,7 +23,7 @@ pub fn parse(json: &Value) -> Vec<AgentEvent> {
         "message" => parse_message(json, &mut events),
         "exec_result" | "tool_result" => parse_exec_result(json, &mut events),
         "session_end" => parse_session_end(json, &mut events),
-        "thinking" => events.push(AgentEvent::Thinking),
+        "thinking" => events.push(AgentEvent::thinking(extract_text(json).as_deref())),
         _ => {
             if let Some(text) = extract_text(json) {
                 events.push(AgentEvent::Text {
@@ -38,7 +42,10 @@ fn parse_session_start(json: &Value, events: &mut Vec<AgentEvent>) {
         .or_else(|| json.get("sessionId"))
         .and_then(Value::as_str)
         .map(String::from);
-    events.push(AgentEvent::SessionStarted { session_id });
+    events.push(AgentEvent::SessionStarted {
+        session_id,
+        metadata: SessionMetadata::default(),
+    });
 }
 
 fn parse_message(json: &Value, events: &mut Vec<AgentEvent>) {
@@ -108,7 +115,12 @@ fn parse_tool_call(block: &Value) -> Option<ToolCall> {
                     serde_json::from_str(args_str).unwrap_or(Value::Null)
                 })
         });
-    Some(ToolCall { id, name, input })
+    Some(ToolCall {
+        id,
+        name,
+        input,
+        server: None,
+    })
 }
 
 fn parse_exec_result(json: &Value, events: &mut Vec<AgentEvent>) {
@@ -141,7 +153,11 @@ fn parse_session_end(json: &Value, events: &mut Vec<AgentEvent>) {
         events.push(AgentEvent::Usage(usage));
     }
     let exit_code = json.get("exit_code").and_then(Value::as_i64).map(|c| c as i32);
-    events.push(AgentEvent::SessionCompleted { exit_code });
+    events.push(AgentEvent::SessionCompleted {
+        exit_code,
+        signal: None,
+        completion_record: true,
+    });
 }
 
 fn parse_usage(json: &Value) -> Option<Usage> {
@@ -0,0 +1,27 @@
+//! Parsers for Codex CLI JSON streaming output.
+//!
+//! Codex has shipped two `exec --json` protocols. Current versions emit
+//! `type`-discriminated thread, turn and item events, while older versions
+//! emit `event`-discriminated messages. The format is detected per line so
+//! both work with the same session.
+
+mod exec;
+mod legacy;
+
+use crate::events::AgentEvent;
+use serde_json::Value;
+
+/// Parses a Codex CLI JSON event into agent events.
+pub fn parse(json: &Value) -> Vec<AgentEvent> {
+    if is_legacy(json) || json.get("type").is_none() {
+        legacy::parse(json)
+    } else {
+        exec::parse(json)
+    }
+}
+
+/// Returns whether the event comes from a legacy Codex version, which
+/// cannot resume sessions.
+pub fn is_legacy(json: &Value) -> bool {
+    json.get("event").is_some()
+}
@@ -1,27 +1,44 @@
 //! Parser for Gemini CLI JSON streaming output.
 //!
 //! Gemini CLI emits JSONL events with a `type` field indicating the event kind.
-//! Known event types include:
-//! - `session_start`: Session initialization
-//! - `text`: Text output
-//! - `tool_call`: Tool invocation
-//! - `tool_result`: Tool execution result
-//! - `session_end`: Session completion with usage
+//! The `-o stream-json` event types are:
+//! - `init`: Session initialization with session ID and model
+//! - `message`: User or assistant message, streamed when `delta` is set;
+//!   assistant messages with `thought` set carry reasoning
+//! - `tool_use`: Tool invocation; an MCP tool is only named `<server>__<tool>`
+//!   when its name collides with another tool
+//! - `tool_result`: Tool execution result with a `status`
+//! - `error`: Non-fatal error or warning
+//! - `result`: Turn completion with `stats`
+//!
+//! Older aliases such as `session_start`, `text`, `tool_call` and
+//! `session_end` are still accepted.
 
-use crate::events::{AgentEvent, ToolCall, ToolResult, Usage};
+use crate::error::ErrorKind;
+use crate::events::{AgentEvent, SessionMetadata, ToolCall, ToolResult, TurnSummary, Usage};
+use crate::mcp;
 use serde_json::Value;
+use std::time::Duration;
 
 /// Parses a Gemini CLI JSON event into agent events.
-pub fn parse(json: &Value) -> Vec<AgentEvent> {
+///
+/// Tool calls are attributed to one of `mcp_servers` only when the tool name
+/// carries that server's prefix.
+pub fn parse(json: &Value, mcp_servers: &[String]) -> Vec<AgentEvent> {
     let mut events = Vec::new();
     let event_type = json.get("type").and_then(Value::as_str).unwrap_or("");
     match event_type {
-        "session_start" | "sessionStart" => parse_session_start(json, &mut events),
+        "init" | "session_start" | "sessionStart" => parse_session_start(json, &mut events),
+        "message" => parse_message(json, &mut events),
         "text" | "content" => parse_text(json, &mut events),
-        "tool_call" | "toolCall" | "function_call" => parse_tool_call_event(json, &mut events),
+        "tool_use" | "tool_call" | "toolCall" | "function_call" => {
+            parse_tool_call_event(json, mcp_servers, &mut events);
+        }
         "tool_result" | "toolResult" | "function_result" => parse_tool_result(json, &mut events),
+        "error" => parse_error(json, &mut events),
+        "result" => parse_result(json, &mut events),
         "session_end" | "sessionEnd" => parse_session_end(json, &mut events),
-        "thinking" => events.push(AgentEvent::Thinking),
+        "thinking" | "thought" => parse_thought(json, &mut events),
         _ => {
             if let Some(text) = extract_text(json) {
                 events.push(AgentEvent::Text {
@@ -40,7 +57,35 @@ fn parse_session_start(json: &Value, events: &mut Vec<AgentEvent>) {
         .or_else(|| json.get("sessionId"))
         .and_then(Value::as_str)
         .map(String::from);
-    events.push(AgentEvent::SessionStarted { session_id });
+    let metadata = SessionMetadata {
+        model: json.get("model").and_then(Value::as_str).map(String::from),
+        ..SessionMetadata::default()
+    };
+    events.push(AgentEvent::SessionStarted {
+        session_id,
+        metadata,
+    });
+}
+
+fn parse_message(json: &Value, events: &mut Vec<AgentEvent>) {
+    if json.get("role").and_then(Value::as_str) != Some("assistant") {
+        return;
+    }
+    let text = json.get("content").and_then(Value::as_str).unwrap_or("");
+    let is_partial = json.get("delta").and_then(Value::as_bool).unwrap_or(false);
+    if json.get("thought").and_then(Value::as_bool) == Some(true) {
+        events.push(AgentEvent::Thinking {
+            content: (!text.is_empty()).then(|| text.to_string()),
+            is_partial,
+            is_redacted: false,
+            signature: None,
+        });
+    } else if !text.is_empty() {
+        events.push(AgentEvent::Text {
+            content: text.to_string(),
+            is_partial,
+        });
+    }
 }
 
 fn parse_text(json: &Value, events: &mut Vec<AgentEvent>) {
@@ -62,37 +107,68 @@ fn parse_text(json: &Value, events: &mut Vec<AgentEvent>) {
     }
 }
 
-fn parse_tool_call_event(json: &Value, events: &mut Vec<AgentEvent>) {
-    if let Some(call) = parse_tool_call(json) {
+/// Parses a thought event. A structured thought's `subject` and
+/// `description` are joined with a colon.
+fn parse_thought(json: &Value, events: &mut Vec<AgentEvent>) {
+    let thought = json.get("thought").or_else(|| json.get("value"));
+    let field = |key: &str| {
+        thought
+            .and_then(|thought| thought.get(key))
+            .and_then(Value::as_str)
+            .filter(|text| !text.is_empty())
+    };
+    let content = match (field("subject"), field("description")) {
+        (Some(subject), Some(description)) => Some(format!("{subject}: {description}")),
+        (subject, description) => subject
+            .or(description)
+            .or_else(|| thought.and_then(Value::as_str))
+            .map(String::from)
+            .or_else(|| extract_text(json)),
+    };
+    events.push(AgentEvent::thinking(content.as_deref()));
+}
+
+fn parse_tool_call_event(json: &Value, mcp_servers: &[String], events: &mut Vec<AgentEvent>) {
+    if let Some(call) = parse_tool_call(json, mcp_servers) {
         events.push(AgentEvent::ToolCall(call));
     }
 }
 
-fn parse_tool_call(json: &Value) -> Option<ToolCall> {
+fn parse_tool_call(json: &Value, mcp_servers: &[String]) -> Option<ToolCall> {
     let id = json
-        .get("id")
+        .get("tool_id")
+        .or_else(|| json.get("id"))
         .or_else(|| json.get("call_id"))
         .or_else(|| json.get("callId"))
         .and_then(Value::as_str)?
         .to_string();
     let name = json
-        .get("name")
+        .get("tool_name")
+        .or_else(|| json.get("name"))
         .or_else(|| json.get("function"))
         .or_else(|| json.get("tool"))
         .and_then(Value::as_str)?
         .to_string();
     let input = json
-        .get("input")
+        .get("parameters")
+        .or_else(|| json.get("input"))
         .or_else(|| json.get("args"))
         .or_else(|| json.get("arguments"))
         .cloned()
         .unwrap_or(Value::Null);
-    Some(ToolCall { id, name, input })
+    let server = mcp::configured_server_of(&name, mcp_servers).map(String::from);
+    Some(ToolCall {
+        id,
+        name,
+        input,
+        server,
+    })
 }
 
 fn parse_tool_result(json: &Value, events: &mut Vec<AgentEvent>) {
     let tool_call_id = json
-        .get("call_id")
+        .get("tool_id")
+        .or_else(|| json.get("call_id"))
         .or_else(|| json.get("callId"))
         .or_else(|| json.get("tool_call_id"))
         .and_then(Value::as_str)
@@ -103,13 +179,18 @@ fn parse_tool_result(json: &Value, events: &mut Vec<AgentEvent>) {
         .or_else(|| json.get("result"))
         .or_else(|| json.get("content"))
         .and_then(Value::as_str)
+        .or_else(|| error_message(json))
         .unwrap_or("")
         .to_string();
-    let success = json
-        .get("success")
-        .or_else(|| json.get("ok"))
-        .and_then(Value::as_bool)
-        .unwrap_or(true);
+    let success = json.get("status").and_then(Value::as_str).map_or_else(
+        || {
+            json.get("success")
+                .or_else(|| json.get("ok"))
+                .and_then(Value::as_bool)
+                .unwrap_or(true)
+        },
+        |status| status != "error",
+    );
     if !tool_call_id.is_empty() {
         events.push(AgentEvent::ToolResult(ToolResult {
             tool_call_id,
@@ -119,13 +200,75 @@ fn parse_tool_result(json: &Value, events: &mut Vec<AgentEvent>) {
     }
 }
 
+fn parse_error(json: &Value, events: &mut Vec<AgentEvent>) {
+    let message = json
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown error");
+    events.push(AgentEvent::Error {
+        kind: ErrorKind::Agent,
+        message: message.to_string(),
+    });
+}
+
+fn parse_result(json: &Value, events: &mut Vec<AgentEvent>) {
+    if let Some(stats) = json.get("stats") {
+        let tokens = |key: &str| stats.get(key).and_then(Value::as_u64);
+        events.push(AgentEvent::Usage(Usage {
+            input_tokens: tokens("input_tokens").unwrap_or(0),
+            output_tokens: tokens("output_tokens").unwrap_or(0),
+            cache_read_tokens: tokens("cached"),
+            cache_write_tokens: None,
+        }));
+    }
+    let is_error = json.get("status").and_then(Value::as_str) == Some("error");
+    if is_error {
+        events.push(AgentEvent::Error {
+            kind: ErrorKind::Agent,
+            message: error_message(json).unwrap_or("unknown error").to_string(),
+        });
+    }
+    let summary = TurnSummary {
+        duration: json
+            .get("stats")
+            .and_then(|stats| stats.get("duration_ms"))
+            .and_then(Value::as_u64)
+            .map(Duration::from_millis),
+        is_error,
+        subtype: json
+            .get("error")
+            .and_then(|error| error.get("type"))
+            .and_then(Value::as_str)
+            .map(String::from),
+        ..TurnSummary::default()
+    };
+    if !summary.is_empty() {
+        events.push(AgentEvent::TurnSummary(summary));
+    }
+    events.push(AgentEvent::SessionCompleted {
+        exit_code: None,
+        signal: None,
+        completion_record: true,
+    });
+}
+
+fn error_message(json: &Value) -> Option<&str> {
+    json.get("error")
+        .and_then(|error| error.get("message"))
+        .and_then(Value::as_str)
+}
+
 #[allow(clippy::cast_possible_truncation)]
 fn parse_session_end(json: &Value, events: &mut Vec<AgentEvent>) {
     if let Some(usage) = parse_usage(json) {
         events.push(AgentEvent::Usage(usage));
     }
     let exit_code = json.get("exit_code").and_then(Value::as_i64).map(|c| c as i32);
-    events.push(AgentEvent::SessionCompleted { exit_code });
+    events.push(AgentEvent::SessionCompleted {
+        exit_code,
+        signal: None,
+        completion_record: true,
+    });
 }
 
 fn parse_usage(json: &Value) -> Option<Usage> {
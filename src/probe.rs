@@ -0,0 +1,280 @@
+//! Detection of installed CLI versions and capabilities.
+//!
+//! A probe runs the CLI's `--version` and `--help` commands and looks for
+//! the flags and subcommands this crate relies on in the help text.
+
+use crate::config::{AgentConfig, AgentKind};
+use crate::error::{Error, Result};
+use crate::process::{apply_context, find_program};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A capability a CLI may or may not offer, depending on its version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CliFeature {
+    /// Streams JSON events on stdout.
+    StreamJson,
+    /// Resumes an earlier session.
+    Resume,
+    /// Reads stream-json messages on stdin, for persistent sessions.
+    StreamJsonInput,
+    /// Accepts a permission or sandbox mode flag.
+    PermissionModes,
+    /// Accepts MCP server configuration.
+    McpConfig,
+    /// Accepts a list of allowed tools.
+    AllowedTools,
+    /// Streams partial messages as they are generated.
+    PartialMessages,
+}
+
+impl CliFeature {
+    /// Returns a short description used in error messages.
+    const fn description(self) -> &'static str {
+        match self {
+            Self::StreamJson => "stream-json output",
+            Self::Resume => "resume",
+            Self::StreamJsonInput => "stream-json input",
+            Self::PermissionModes => "permission modes",
+            Self::McpConfig => "MCP servers",
+            Self::AllowedTools => "allowed tools",
+            Self::PartialMessages => "partial messages",
+        }
+    }
+}
+
+/// What a probe found out about an installed CLI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliInfo {
+    /// The version reported by `--version`, if it could be parsed.
+    pub version: Option<String>,
+    /// The binary that was probed.
+    pub path: PathBuf,
+    /// The features found in the help text.
+    pub supported_features: Vec<CliFeature>,
+}
+
+impl CliInfo {
+    /// Returns whether the probe found `feature`.
+    #[must_use]
+    pub fn supports(&self, feature: CliFeature) -> bool {
+        self.supported_features.contains(&feature)
+    }
+}
+
+impl AgentKind {
+    /// Runs the CLI found on PATH to detect its version and features.
+    ///
+    /// Custom kinds only report their version. Each call starts the CLI
+    /// twice, so callers should keep the result rather than probe per turn.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::BinaryNotFound` if the binary is not on PATH.
+    pub fn probe(&self) -> Result<CliInfo> {
+        let name = self.binary_name();
+        let path_var = std::env::var_os("PATH");
+        let path = find_program(Path::new(name), path_var.as_deref()).ok_or_else(|| {
+            Error::BinaryNotFound {
+                cli_name: name.to_string(),
+            }
+        })?;
+        Ok(probe_at(self, path))
+    }
+}
+
+/// Probes the binary at `path`, which is assumed to exist.
+pub fn probe_at(kind: &AgentKind, path: PathBuf) -> CliInfo {
+    probe_with(kind, path, |_| {})
+}
+
+/// Probes the binary at `path`, letting `configure` prepare each command.
+fn probe_with(kind: &AgentKind, path: PathBuf, configure: impl Fn(&mut Command)) -> CliInfo {
+    let version = run(&path, &["--version"], &configure).and_then(|out| parse_version(&out));
+    let help_args: &[&str] = match kind {
+        AgentKind::Codex => &["exec", "--help"],
+        _ => &["--help"],
+    };
+    let help = run(&path, help_args, &configure).unwrap_or_default();
+    let supported_features = feature_markers(kind)
+        .iter()
+        .filter(|(_, marker)| marker.found_in(&help))
+        .map(|(feature, _)| *feature)
+        .collect();
+    CliInfo {
+        version,
+        path,
+        supported_features,
+    }
+}
+
+/// Rejects a configuration that needs features the installed CLI lacks.
+///
+/// The CLI runs with the configured working directory and environment.
+/// `cached` holds the result of an earlier probe and is only replaced when
+/// it describes a different binary than `path`. CLIs whose version cannot
+/// be read are not checked, since their help text cannot be trusted to list
+/// every flag.
+///
+/// # Errors
+///
+/// Returns `Error::UnsupportedOption` naming the CLI version and the first
+/// missing feature.
+pub fn preflight(config: &AgentConfig, path: PathBuf, cached: &mut Option<CliInfo>) -> Result<()> {
+    if matches!(config.kind, AgentKind::Custom(_)) {
+        return Ok(());
+    }
+    if cached.as_ref().is_none_or(|info| info.path != path) {
+        *cached = Some(probe_with(&config.kind, path, |cmd| {
+            apply_context(cmd, config);
+        }));
+    }
+    let Some(info) = cached.as_ref() else {
+        return Ok(());
+    };
+    let Some(ref version) = info.version else {
+        return Ok(());
+    };
+    required_features(config)
+        .into_iter()
+        .find(|feature| !info.supports(*feature))
+        .map_or(Ok(()), |feature| {
+            Err(Error::UnsupportedOption {
+                cli_kind: format!("{} {version}", config.kind.binary_name()),
+                option: feature.description().to_string(),
+            })
+        })
+}
+
+fn required_features(config: &AgentConfig) -> Vec<CliFeature> {
+    let mut features = vec![CliFeature::StreamJson];
+    if config.session_id.is_some() {
+        features.push(CliFeature::Resume);
+    }
+    if config.persistent {
+        features.push(CliFeature::StreamJsonInput);
+    }
+    if config.permission_mode.is_some() {
+        features.push(CliFeature::PermissionModes);
+    }
+    if !config.mcp_servers.is_empty() {
+        features.push(CliFeature::McpConfig);
+    }
+    if !config.allowed_tools.is_empty() {
+        features.push(CliFeature::AllowedTools);
+    }
+    if config.partial_messages && config.kind == AgentKind::Claude {
+        features.push(CliFeature::PartialMessages);
+    }
+    features
+}
+
+/// How a feature shows up in a CLI's help text.
+#[derive(Clone, Copy)]
+enum Marker {
+    /// The text appears anywhere, as a flag or a flag value does.
+    Text(&'static str),
+    /// An indented line of the command list starts with the subcommand.
+    Subcommand(&'static str),
+}
+
+impl Marker {
+    fn found_in(self, help: &str) -> bool {
+        match self {
+            Self::Text(text) => help.contains(text),
+            Self::Subcommand(name) => help.lines().any(|line| {
+                line.starts_with(char::is_whitespace)
+                    && line.split_whitespace().next() == Some(name)
+            }),
+        }
+    }
+}
+
+/// Returns the help-text marker for each feature a CLI can offer.
+const fn feature_markers(kind: &AgentKind) -> &'static [(CliFeature, Marker)] {
+    use CliFeature::{
+        AllowedTools, McpConfig, PartialMessages, PermissionModes, Resume, StreamJson,
+        StreamJsonInput,
+    };
+    use Marker::{Subcommand, Text};
+    match kind {
+        AgentKind::Claude => &[
+            (StreamJson, Text("stream-json")),
+            (Resume, Text("--resume")),
+            (StreamJsonInput, Text("--input-format")),
+            (PermissionModes, Text("--permission-mode")),
+            (McpConfig, Text("--mcp-config")),
+            (AllowedTools, Text("--allowedTools")),
+            (PartialMessages, Text("--include-partial-messages")),
+        ],
+        AgentKind::Codex => &[
+            (StreamJson, Text("--json")),
+            (Resume, Subcommand("resume")),
+            (PermissionModes, Text("--sandbox")),
+            (McpConfig, Text("--config")),
+        ],
+        AgentKind::Gemini => &[
+            (StreamJson, Text("stream-json")),
+            (Resume, Text("--resume")),
+            (PermissionModes, Text("--approval-mode")),
+            (McpConfig, Text("--allowed-mcp-server-names")),
+            (AllowedTools, Text("--allowed-tools")),
+        ],
+        AgentKind::Custom(_) => &[],
+    }
+}
+
+/// Extracts the first dotted version number, such as `0.20.0` from
+/// `codex-cli 0.20.0`.
+fn parse_version(output: &str) -> Option<String> {
+    output
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| {
+            word.split('.').count() >= 2
+                && word.starts_with(|c: char| c.is_ascii_digit())
+                && word
+                    .split(['.', '-'])
+                    .next()
+                    .is_some_and(|major| major.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(String::from)
+}
+
+/// Runs `program` and returns its stdout if it exits successfully within
+/// the probe timeout.
+fn run(program: &Path, args: &[&str], configure: impl Fn(&mut Command)) -> Option<String> {
+    let mut cmd = Command::new(program);
+    configure(&mut cmd);
+    let mut child = cmd
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut out = String::new();
+        stdout.read_to_string(&mut out).map(|_| out)
+    });
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+        }
+    };
+    let out = reader.join().ok()?.ok()?;
+    status.filter(ExitStatus::success).map(|_| out)
+}
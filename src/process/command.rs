@@ -0,0 +1,251 @@
+//! Command lines for the supported agent CLIs.
+
+use super::prompt::{self, TempFile};
+use super::signal;
+use crate::config::{AgentConfig, AgentKind, PermissionMode, PromptDelivery};
+use crate::error::{Error, Result};
+use crate::mcp::{self, McpServer};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Builds the command for one turn of the configured CLI.
+///
+/// `settings` is the file written by [`write_settings`], if any.
+pub fn build(config: &AgentConfig, prompt: &str, settings: Option<&TempFile>) -> Command {
+    let settings = settings.map(TempFile::path);
+    let mut cmd = match config.kind {
+        AgentKind::Claude => build_claude(config, prompt, settings),
+        AgentKind::Codex => build_codex(config, prompt),
+        AgentKind::Gemini => build_gemini(config, prompt),
+        AgentKind::Custom(ref backend) => backend.build_command(config, prompt),
+    };
+    apply_context(&mut cmd, config);
+    if let (AgentKind::Gemini, Some(path)) = (&config.kind, settings) {
+        cmd.env("GEMINI_CLI_SYSTEM_SETTINGS_PATH", path);
+    }
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    signal::isolate(&mut cmd);
+    cmd
+}
+
+/// Writes the private file through which Claude or Gemini receives MCP
+/// servers, keeping their environment out of the command line.
+///
+/// The returned file must outlive the process, since the CLI may read its
+/// settings after startup.
+///
+/// # Errors
+///
+/// Returns `Error::SpawnFailed` if the file cannot be written.
+pub fn write_settings(config: &AgentConfig) -> Result<Option<TempFile>> {
+    if config.mcp_servers.is_empty() {
+        return Ok(None);
+    }
+    let settings = match config.kind {
+        AgentKind::Claude => mcp::claude_config(&config.mcp_servers),
+        AgentKind::Gemini => mcp::gemini_settings(&config.mcp_servers),
+        _ => return Ok(None),
+    };
+    let (_, file) =
+        prompt::write_file("settings", &settings).map_err(|e| Error::SpawnFailed { source: e })?;
+    Ok(Some(file))
+}
+
+/// Rejects options the configured CLI has no way to express.
+///
+/// # Errors
+///
+/// Returns `Error::UnsupportedOption` naming the first such option.
+pub fn check_supported(config: &AgentConfig) -> Result<()> {
+    let system_prompt = (
+        "appended system prompts",
+        config.append_system_prompt.is_some(),
+    );
+    let allowed = ("allowed tools", !config.allowed_tools.is_empty());
+    let disallowed = ("disallowed tools", !config.disallowed_tools.is_empty());
+    let max_turns = ("max turns", config.max_turns.is_some());
+    let partial = ("partial messages", config.partial_messages);
+    let server_env = (
+        "environment variables for MCP servers",
+        config.mcp_servers.iter().any(McpServer::has_env),
+    );
+    let unsupported = match config.kind {
+        AgentKind::Claude => vec![],
+        AgentKind::Codex => vec![allowed, disallowed, max_turns, partial, server_env],
+        AgentKind::Gemini => vec![system_prompt, disallowed, max_turns],
+        AgentKind::Custom(ref backend) => return backend.validate(config),
+    };
+    let bad_server = config.mcp_servers.iter().find(|s| !s.has_valid_name());
+    let option = match unsupported.into_iter().find(|(_, used)| *used) {
+        Some((option, _)) => option.to_string(),
+        None => match (config.permission_mode, bad_server) {
+            (Some(mode), _) if permission_args(&config.kind, mode).is_none() => {
+                format!("the {mode:?} permission mode")
+            }
+            (_, Some(server)) => format!("the MCP server name {:?}", server.name),
+            _ => return Ok(()),
+        },
+    };
+    Err(Error::UnsupportedOption {
+        cli_kind: config.kind.display_name().to_string(),
+        option,
+    })
+}
+
+/// Returns the native flags for `mode`, or `None` if the CLI has no
+/// equivalent that is at most as permissive.
+const fn permission_args(
+    kind: &AgentKind,
+    mode: PermissionMode,
+) -> Option<&'static [&'static str]> {
+    use PermissionMode::{AcceptEdits, Bypass, FullAuto, ReadOnly, WorkspaceWrite};
+    let args: &[&str] = match (kind, mode) {
+        (AgentKind::Claude, ReadOnly) => &["--permission-mode", "plan"],
+        (AgentKind::Claude, AcceptEdits) => &["--permission-mode", "acceptEdits"],
+        (AgentKind::Claude, Bypass) => &["--permission-mode", "bypassPermissions"],
+        (AgentKind::Codex, ReadOnly) => &["--sandbox", "read-only"],
+        (AgentKind::Codex, WorkspaceWrite) => &["--sandbox", "workspace-write"],
+        (AgentKind::Codex, FullAuto) => &["--full-auto"],
+        (AgentKind::Codex, Bypass) => &["--dangerously-bypass-approvals-and-sandbox"],
+        (AgentKind::Gemini, AcceptEdits) => &["--approval-mode", "auto_edit"],
+        (AgentKind::Gemini, Bypass) => &["--approval-mode", "yolo"],
+        _ => return None,
+    };
+    Some(args)
+}
+
+/// Adds the permission flags, falling back to the CLI's dangerous flag for
+/// `skip_permissions`.
+fn add_permission_args(cmd: &mut Command, config: &AgentConfig, skip_flag: &str) {
+    match config.permission_mode {
+        Some(mode) => {
+            cmd.args(permission_args(&config.kind, mode).unwrap_or_default());
+        }
+        None if config.skip_permissions => {
+            cmd.arg(skip_flag);
+        }
+        None => {}
+    }
+}
+
+/// Applies the configured working directory and environment to `cmd`.
+pub fn apply_context(cmd: &mut Command, config: &AgentConfig) {
+    if let Some(ref dir) = config.working_dir {
+        cmd.current_dir(dir);
+    }
+    apply_env(cmd, config);
+}
+
+fn apply_env(cmd: &mut Command, config: &AgentConfig) {
+    if let Some(ref allowlist) = config.env_allowlist {
+        cmd.env_clear();
+        for key in allowlist {
+            if let Some(value) = std::env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+    }
+    for (key, value) in &config.env {
+        match value {
+            Some(value) => cmd.env(key, value),
+            None => cmd.env_remove(key),
+        };
+    }
+}
+
+fn build_claude(config: &AgentConfig, prompt: &str, mcp_config: Option<&Path>) -> Command {
+    let mut cmd = Command::new(config.program());
+    cmd.arg("--print");
+    if !config.allowed_tools.is_empty() {
+        cmd.arg("--allowedTools")
+            .arg(config.allowed_tools.join(","));
+    }
+    if !config.disallowed_tools.is_empty() {
+        cmd.arg("--disallowedTools")
+            .arg(config.disallowed_tools.join(","));
+    }
+    if let Some(path) = mcp_config {
+        cmd.arg("--mcp-config").arg(path);
+    }
+    cmd.args(&config.extra_args);
+    cmd.arg("--output-format").arg("stream-json");
+    if config.persistent {
+        cmd.arg("--input-format").arg("stream-json");
+        cmd.stdin(Stdio::piped());
+    }
+    if config.partial_messages {
+        cmd.arg("--include-partial-messages");
+    }
+    if config.persistent || config.partial_messages {
+        cmd.arg("--verbose");
+    }
+    add_permission_args(&mut cmd, config, "--dangerously-skip-permissions");
+    if let Some(ref model) = config.model {
+        cmd.arg("--model").arg(model);
+    }
+    if let Some(ref system_prompt) = config.append_system_prompt {
+        cmd.arg("--append-system-prompt").arg(system_prompt);
+    }
+    if let Some(max_turns) = config.max_turns {
+        cmd.arg("--max-turns").arg(max_turns.to_string());
+    }
+    if let Some(ref session_id) = config.session_id {
+        cmd.arg("--resume").arg(session_id);
+    }
+    if !config.persistent && config.prompt_delivery == PromptDelivery::Argv {
+        cmd.arg(prompt);
+    }
+    cmd
+}
+
+fn build_codex(config: &AgentConfig, prompt: &str) -> Command {
+    let mut cmd = Command::new(config.program());
+    cmd.arg("exec");
+    cmd.args(&config.extra_args);
+    cmd.arg("--json");
+    add_permission_args(
+        &mut cmd,
+        config,
+        "--dangerously-bypass-approvals-and-sandbox",
+    );
+    if let Some(ref model) = config.model {
+        cmd.arg("--model").arg(model);
+    }
+    if let Some(ref system_prompt) = config.append_system_prompt {
+        let value = serde_json::Value::from(system_prompt.as_str());
+        cmd.arg("-c").arg(format!("developer_instructions={value}"));
+    }
+    for value in mcp::codex_overrides(&config.mcp_servers) {
+        cmd.arg("-c").arg(value);
+    }
+    if let Some(ref thread_id) = config.session_id {
+        cmd.arg("resume").arg(thread_id);
+    }
+    if config.prompt_delivery == PromptDelivery::Argv {
+        cmd.arg(prompt);
+    } else {
+        cmd.arg("-");
+    }
+    cmd
+}
+
+fn build_gemini(config: &AgentConfig, prompt: &str) -> Command {
+    let mut cmd = Command::new(config.program());
+    if !config.allowed_tools.is_empty() {
+        cmd.arg("--allowed-tools")
+            .arg(config.allowed_tools.join(","));
+    }
+    cmd.args(&config.extra_args);
+    cmd.arg("-o").arg("stream-json");
+    add_permission_args(&mut cmd, config, "--yolo");
+    if let Some(ref model) = config.model {
+        cmd.arg("--model").arg(model);
+    }
+    if let Some(ref session_id) = config.session_id {
+        cmd.arg("--resume").arg(session_id);
+    }
+    if config.prompt_delivery == PromptDelivery::Argv {
+        cmd.arg(prompt);
+    }
+    cmd
+}
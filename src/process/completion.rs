@@ -0,0 +1,83 @@
+//! Authoritative completion reporting for finished turns.
+
+use super::watchdog::Expired;
+use crate::events::AgentEvent;
+use std::process::ExitStatus;
+
+/// What the CLI itself reported about the end of a turn.
+///
+/// Parsers emit `SessionCompleted` when they see a completion record such as
+/// Claude's `result` event. The runner absorbs those and emits a single
+/// completion event once the process has been reaped. In a persistent
+/// session each record ends a turn and is forwarded as `TurnCompleted`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompletionRecord {
+    seen: bool,
+    exit_code: Option<i32>,
+    persistent: bool,
+}
+
+impl CompletionRecord {
+    /// Creates a record for a process that handles several turns.
+    pub const fn persistent() -> Self {
+        Self {
+            seen: false,
+            exit_code: None,
+            persistent: true,
+        }
+    }
+
+    /// Records a parser-emitted completion event.
+    ///
+    /// Returns the event to forward, or `None` if it was absorbed.
+    pub fn observe(&mut self, event: AgentEvent) -> Option<AgentEvent> {
+        if let AgentEvent::SessionCompleted { exit_code, .. } = event {
+            self.seen = true;
+            if exit_code.is_some() {
+                self.exit_code = exit_code;
+            }
+            return self.persistent.then_some(AgentEvent::TurnCompleted);
+        }
+        Some(event)
+    }
+
+    fn into_event(self, status: Option<ExitStatus>) -> AgentEvent {
+        AgentEvent::SessionCompleted {
+            exit_code: status.map_or(self.exit_code, |s| s.code()),
+            signal: status.and_then(exit_signal),
+            completion_record: self.seen,
+        }
+    }
+}
+
+/// Builds the events that close a turn after its process was reaped.
+///
+/// A fired timeout is reported first, then the completion event, and a
+/// cancelled turn always ends with `Cancelled`.
+pub fn closing_events(
+    expired: Option<Expired>,
+    record: CompletionRecord,
+    status: Option<ExitStatus>,
+    cancelled: bool,
+) -> Vec<AgentEvent> {
+    let mut events = Vec::with_capacity(2);
+    if let Some(expired) = expired.filter(|_| !cancelled) {
+        events.push(expired.event());
+    }
+    events.push(record.into_event(status));
+    if cancelled {
+        events.push(AgentEvent::Cancelled);
+    }
+    events
+}
+
+#[cfg(unix)]
+fn exit_signal(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+const fn exit_signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
@@ -0,0 +1,301 @@
+//! Shared control over a running CLI process.
+
+use super::signal::{self, Signal};
+#[cfg(not(unix))]
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Shared state of one turn's CLI process.
+///
+/// The driver that owns the child reports its exit here, while timeouts,
+/// cancellation and drop signal the process group by pid. Signals are only
+/// sent while the process has not been reaped. Where process groups cannot
+/// be signalled, the driver registers a killer instead.
+#[derive(Debug)]
+pub struct TurnControl {
+    pid: u32,
+    reaped: Mutex<bool>,
+    reaped_cond: Condvar,
+    cancelled: AtomicBool,
+    resumable: AtomicBool,
+    grace_period: Duration,
+    turn_started: Mutex<Option<Instant>>,
+    #[cfg(not(unix))]
+    killer: Mutex<Option<Killer>>,
+}
+
+/// Kills the CLI itself, for platforms without process group signals.
+#[cfg(not(unix))]
+struct Killer(Box<dyn FnMut() + Send>);
+
+#[cfg(not(unix))]
+impl std::fmt::Debug for Killer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Killer")
+    }
+}
+
+impl TurnControl {
+    /// Creates the control for a freshly spawned process.
+    ///
+    /// The process is considered to be working on a turn from now on.
+    pub fn new(pid: u32, grace_period: Duration) -> Self {
+        Self {
+            pid,
+            reaped: Mutex::new(false),
+            reaped_cond: Condvar::new(),
+            cancelled: AtomicBool::new(false),
+            resumable: AtomicBool::new(true),
+            grace_period,
+            turn_started: Mutex::new(Some(Instant::now())),
+            #[cfg(not(unix))]
+            killer: Mutex::new(None),
+        }
+    }
+
+    /// Records that a persistent process was given a new turn.
+    pub fn begin_turn(&self) {
+        if let Ok(mut started) = self.turn_started.lock() {
+            *started = Some(Instant::now());
+        }
+    }
+
+    /// Records that a persistent process finished its turn and is waiting.
+    pub fn end_turn(&self) {
+        if let Ok(mut started) = self.turn_started.lock() {
+            *started = None;
+        }
+    }
+
+    /// Returns when the current turn started, or `None` between turns.
+    pub fn turn_started(&self) -> Option<Instant> {
+        self.turn_started.lock().ok().and_then(|started| *started)
+    }
+
+    /// Sends `signal` to the process group if the process is still running.
+    ///
+    /// Without process group signals, any signal kills the CLI through the
+    /// registered killer.
+    pub fn signal(&self, signal: Signal) {
+        if let Ok(reaped) = self.reaped.lock() {
+            if !*reaped {
+                signal::send_group(self.pid, signal);
+                #[cfg(not(unix))]
+                if let Ok(mut killer) = self.killer.lock() {
+                    if let Some(Killer(kill)) = killer.as_mut() {
+                        kill();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Registers how to kill the CLI, as its process group cannot be
+    /// signalled on this platform.
+    #[cfg(not(unix))]
+    pub fn set_killer(&self, kill: impl FnMut() + Send + 'static) {
+        if let Ok(mut killer) = self.killer.lock() {
+            *killer = Some(Killer(Box::new(kill)));
+        }
+    }
+
+    /// Kills the process group immediately.
+    pub fn kill(&self) {
+        self.signal(Signal::Kill);
+    }
+
+    /// Records that the process has been reaped.
+    pub fn mark_reaped(&self) {
+        if let Ok(mut reaped) = self.reaped.lock() {
+            *reaped = true;
+            self.reaped_cond.notify_all();
+        }
+    }
+
+    /// Blocks until the process has been reaped or `timeout` elapses.
+    ///
+    /// Returns whether the process has been reaped.
+    pub fn wait_reaped(&self, timeout: Duration) -> bool {
+        let Ok(reaped) = self.reaped.lock() else {
+            return false;
+        };
+        self.reaped_cond
+            .wait_timeout_while(reaped, timeout, |reaped| !*reaped)
+            .is_ok_and(|(reaped, _)| *reaped)
+    }
+
+    /// Returns whether the turn was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Records that the CLI speaks a protocol whose sessions cannot be
+    /// resumed.
+    pub fn mark_unresumable(&self) {
+        self.resumable.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns whether a follow-up turn may resume the CLI's session.
+    pub fn is_resumable(&self) -> bool {
+        self.resumable.load(Ordering::SeqCst)
+    }
+
+    /// Starts graceful cancellation on a background thread.
+    ///
+    /// Sends `SIGINT`, then `SIGTERM` and finally `SIGKILL`, waiting the grace
+    /// period for the process to exit between each step.
+    fn cancel(self: &Arc<Self>) {
+        if self.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let control = Arc::clone(self);
+        thread::spawn(move || {
+            for signal in [Signal::Interrupt, Signal::Terminate] {
+                control.signal(signal);
+                if control.wait_reaped(control.grace_period) {
+                    return;
+                }
+            }
+            control.kill();
+        });
+    }
+}
+
+/// A child process the runners can kill and poll without blocking.
+#[cfg(not(unix))]
+pub trait Reapable: Send + 'static {
+    /// Starts killing the process.
+    fn kill(&mut self);
+
+    /// Returns the exit status if the process has exited.
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>>;
+}
+
+#[cfg(not(unix))]
+impl Reapable for std::process::Child {
+    fn kill(&mut self) {
+        let _ = Self::kill(self);
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        Self::try_wait(self)
+    }
+}
+
+#[cfg(all(not(unix), feature = "tokio"))]
+impl Reapable for tokio::process::Child {
+    fn kill(&mut self) {
+        let _ = self.start_kill();
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        Self::try_wait(self)
+    }
+}
+
+/// A child shared with its [`TurnControl`], which kills it directly where
+/// its process group cannot be signalled.
+#[cfg(not(unix))]
+pub struct SharedChild<C>(Arc<Mutex<C>>);
+
+#[cfg(not(unix))]
+impl<C: Reapable> SharedChild<C> {
+    /// How often `wait` checks whether the child has exited.
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    /// Shares `child` with `control`.
+    pub fn new(child: C, control: &TurnControl) -> Self {
+        let shared = Arc::new(Mutex::new(child));
+        let killed = Arc::clone(&shared);
+        control.set_killer(move || {
+            if let Ok(mut child) = killed.lock() {
+                child.kill();
+            }
+        });
+        Self(shared)
+    }
+
+    fn try_wait(&self) -> std::io::Result<Option<ExitStatus>> {
+        self.0
+            .lock()
+            .map_err(|_| std::io::Error::other("child lock poisoned"))?
+            .try_wait()
+    }
+}
+
+#[cfg(not(unix))]
+impl SharedChild<std::process::Child> {
+    /// Polls the child until it exits, releasing it in between so that the
+    /// control can kill it.
+    pub fn wait(&self) -> std::io::Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            thread::sleep(Self::POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(all(not(unix), feature = "tokio"))]
+impl SharedChild<tokio::process::Child> {
+    /// Polls the child until it exits, releasing it in between so that the
+    /// control can kill it.
+    pub async fn wait(&self) -> std::io::Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            tokio::time::sleep(Self::POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// A cloneable handle for cancelling a session's running turn.
+///
+/// The handle follows the session across turns: cancelling always targets
+/// the turn that is currently running, and has no effect once it finished.
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+    turn: Arc<Mutex<Arc<TurnControl>>>,
+}
+
+impl CancelHandle {
+    pub(crate) fn new(turn: Arc<TurnControl>) -> Self {
+        Self {
+            turn: Arc::new(Mutex::new(turn)),
+        }
+    }
+
+    /// Points the handle at a new turn.
+    pub(crate) fn replace(&self, turn: Arc<TurnControl>) {
+        if let Ok(mut current) = self.turn.lock() {
+            *current = turn;
+        }
+    }
+
+    /// Requests cancellation of the running turn without blocking.
+    ///
+    /// The CLI receives `SIGINT` first and is escalated to `SIGTERM` and
+    /// `SIGKILL` after the configured grace period. The turn's event stream
+    /// ends with [`AgentEvent::Cancelled`](crate::AgentEvent::Cancelled).
+    ///
+    /// The signals reach the CLI's whole process group. On platforms without
+    /// process groups the CLI itself is killed right away. A persistent
+    /// session keeps a single process for all turns, so cancelling one of its
+    /// turns ends the session.
+    pub fn cancel(&self) {
+        if let Ok(current) = self.turn.lock() {
+            current.cancel();
+        }
+    }
+
+    /// Returns whether the running turn has been cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.turn.lock().is_ok_and(|current| current.is_cancelled())
+    }
+}
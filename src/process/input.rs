@@ -0,0 +1,36 @@
+//! User message delivery to a persistent CLI over stdin.
+
+use crate::error::{Error, Result};
+use serde_json::json;
+use std::io::Write;
+use std::process::ChildStdin;
+
+/// Writes user messages to a CLI reading `stream-json` input.
+pub struct InputWriter {
+    stdin: ChildStdin,
+}
+
+impl InputWriter {
+    /// Wraps the stdin pipe of a persistent CLI process.
+    pub const fn new(stdin: ChildStdin) -> Self {
+        Self { stdin }
+    }
+
+    /// Writes `prompt` as one JSONL user message and flushes it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::StdinWriteFailed` if the process closed its stdin.
+    pub fn send(&mut self, prompt: &str) -> Result<()> {
+        let message = json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [{ "type": "text", "text": prompt }],
+            },
+        });
+        writeln!(self.stdin, "{message}")
+            .and_then(|()| self.stdin.flush())
+            .map_err(|e| Error::StdinWriteFailed { source: e })
+    }
+}
@@ -0,0 +1,231 @@
+//! Process spawning and management for agent CLIs.
+
+mod command;
+mod completion;
+mod control;
+mod input;
+mod prompt;
+mod resolve;
+mod sequence;
+mod signal;
+mod watchdog;
+
+pub use command::{apply_context, check_supported};
+#[cfg(feature = "tokio")]
+pub use command::{build as build_command, write_settings};
+pub use completion::{closing_events, CompletionRecord};
+#[cfg(not(unix))]
+pub use control::SharedChild;
+pub use control::{CancelHandle, TurnControl};
+#[cfg(feature = "tokio")]
+pub use prompt::{attach as attach_prompt, TempFile};
+pub use resolve::{find_program, resolve as resolve_program};
+pub use sequence::Sequence;
+#[cfg(feature = "tokio")]
+pub use watchdog::Expired;
+pub use watchdog::Timeouts;
+
+use crate::config::AgentConfig;
+use crate::error::{Error, ErrorKind, Result};
+use crate::events::{AgentEvent, EventEnvelope, EventSource};
+use crate::stream::{read_stderr, StreamReader};
+use input::InputWriter;
+use std::io;
+use std::process::ChildStdin;
+use std::sync::mpsc::{self, sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+
+/// Handle to a running CLI process.
+pub struct ProcessHandle {
+    control: Arc<TurnControl>,
+    input: Option<InputWriter>,
+    stdout_thread: Option<thread::JoinHandle<()>>,
+    _settings: Option<prompt::TempFile>,
+}
+
+impl ProcessHandle {
+    /// Spawns a new CLI process with the given configuration and prompt.
+    ///
+    /// The stdout thread owns the child and drives the turn: once stdout
+    /// closes it joins the stderr reader and the timeout watchdog, reaps the
+    /// process and sends the closing events, which are always the last ones.
+    ///
+    /// With `PromptDelivery::Stdin` the prompt is written on its own thread,
+    /// so a CLI that reads stdin late cannot block the spawn; a failed write
+    /// is reported as an `ErrorKind::Input` event before the closing events.
+    /// A persistent process receives `prompt` on stdin and keeps running
+    /// until the handle is dropped. Its events are numbered by `sequence`.
+    pub fn spawn(
+        config: &AgentConfig,
+        prompt: &str,
+        sequence: Sequence,
+    ) -> Result<(Self, Receiver<EventEnvelope>)> {
+        let settings = command::write_settings(config)?;
+        let mut cmd = command::build(config, prompt, settings.as_ref());
+        let prompt_file = prompt::attach(&mut cmd, config, prompt)?;
+        let mut child = cmd.spawn().map_err(|e| Error::SpawnFailed { source: e })?;
+        drop(prompt_file);
+        let stdin = child.stdin.take();
+        let (input, prompt_pipe) = if config.persistent {
+            (stdin.map(InputWriter::new), None)
+        } else {
+            (None, stdin)
+        };
+        let buffer_size = config.channel_buffer_size;
+        let (channel, receiver) = if buffer_size == 0 {
+            let (tx, rx) = std::sync::mpsc::channel();
+            (SyncSenderWrapper::Unbounded(tx), rx)
+        } else {
+            let (tx, rx) = sync_channel(buffer_size);
+            (SyncSenderWrapper::Bounded(tx), rx)
+        };
+        let sender = EventSender { channel, sequence };
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let control = Arc::new(TurnControl::new(child.id(), config.cancel_grace_period));
+        #[cfg(not(unix))]
+        let child = SharedChild::new(child, &control);
+        let stderr_sender = sender.clone();
+        let stderr_thread = stderr.map(|err| {
+            thread::spawn(move || {
+                read_stderr(err, &stderr_sender);
+            })
+        });
+        let prompt_thread = prompt_pipe.map(|pipe| {
+            let prompt = prompt.to_string();
+            let prompt_sender = sender.clone();
+            thread::spawn(move || write_prompt(pipe, &prompt, &prompt_sender))
+        });
+        let timeouts = Timeouts::from_config(config);
+        let (activity, watchdog_thread) = if timeouts.is_enabled() {
+            let (tx, rx) = mpsc::channel();
+            let watched = Arc::clone(&control);
+            let handle = thread::spawn(move || watchdog::watch(&watched, &rx, timeouts));
+            (Some(tx), Some(handle))
+        } else {
+            (None, None)
+        };
+        let reader = stdout.map(|out| {
+            let reader = StreamReader::new(out, config, Arc::clone(&control));
+            if config.persistent {
+                reader.with_turns()
+            } else {
+                reader
+            }
+        });
+        let driven = Arc::clone(&control);
+        let stdout_thread = thread::spawn(move || {
+            let record = if let Some(reader) = reader {
+                reader.with_activity(activity).read_to_channel(&sender)
+            } else {
+                drop(activity);
+                CompletionRecord::default()
+            };
+            let expired = watchdog_thread.and_then(|handle| handle.join().ok().flatten());
+            if let Some(handle) = stderr_thread {
+                let _ = handle.join();
+            }
+            let status = child.wait().ok();
+            driven.mark_reaped();
+            if let Some(handle) = prompt_thread {
+                let _ = handle.join();
+            }
+            let closed_at = SystemTime::now();
+            for event in closing_events(expired, record, status, driven.is_cancelled()) {
+                if sender.send(EventSource::Runner, closed_at, event).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut handle = Self {
+            control,
+            input,
+            stdout_thread: Some(stdout_thread),
+            _settings: settings,
+        };
+        if handle.input.is_some() {
+            handle.send(prompt)?;
+        }
+        Ok((handle, receiver))
+    }
+
+    /// Sends the next user message to a persistent process.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::StdinWriteFailed` if the process is not persistent or
+    /// no longer accepts input.
+    pub fn send(&mut self, prompt: &str) -> Result<()> {
+        let input = self.input.as_mut().ok_or_else(|| Error::StdinWriteFailed {
+            source: io::Error::new(io::ErrorKind::NotConnected, "process has no stdin pipe"),
+        })?;
+        self.control.begin_turn();
+        input.send(prompt)
+    }
+
+    /// Returns the shared control for this process.
+    pub fn control(&self) -> Arc<TurnControl> {
+        Arc::clone(&self.control)
+    }
+}
+
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        drop(self.input.take());
+        self.control.kill();
+        if let Some(handle) = self.stdout_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Writes the prompt to the child's stdin and closes it.
+///
+/// Failures are reported in the event stream, since spawning has already
+/// returned by the time the write completes.
+fn write_prompt(mut pipe: ChildStdin, prompt: &str, sender: &EventSender) {
+    if let Err(e) = prompt::write_prompt(&mut pipe, prompt) {
+        let event = AgentEvent::Error {
+            kind: ErrorKind::Input,
+            message: e.to_string(),
+        };
+        let _ = sender.send(EventSource::Runner, SystemTime::now(), event);
+    }
+}
+
+/// Wrapper to support both bounded and unbounded channels.
+#[derive(Clone)]
+enum SyncSenderWrapper {
+    /// Bounded sync channel.
+    Bounded(SyncSender<EventEnvelope>),
+    /// Unbounded channel.
+    Unbounded(std::sync::mpsc::Sender<EventEnvelope>),
+}
+
+/// The sending side of a process's event channel, which numbers events.
+#[derive(Clone)]
+pub struct EventSender {
+    channel: SyncSenderWrapper,
+    sequence: Sequence,
+}
+
+impl EventSender {
+    /// Sends an event that arrived from `source` at `received_at`.
+    ///
+    /// Fails once the receiver is gone; the event is dropped, since no
+    /// caller resends it.
+    pub fn send(
+        &self,
+        source: EventSource,
+        received_at: SystemTime,
+        event: AgentEvent,
+    ) -> std::result::Result<(), ()> {
+        self.sequence
+            .queue(source, received_at, event, |envelope| match self.channel {
+                SyncSenderWrapper::Bounded(ref tx) => tx.send(envelope).map_err(drop),
+                SyncSenderWrapper::Unbounded(ref tx) => tx.send(envelope).map_err(drop),
+            })
+    }
+}
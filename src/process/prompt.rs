@@ -0,0 +1,93 @@
+//! Prompt delivery through the child's stdin.
+
+use crate::config::{AgentConfig, PromptDelivery};
+use crate::error::{Error, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// A private temporary file that is removed when dropped.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Returns the location of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Connects the child's stdin according to the configured prompt delivery.
+///
+/// With `PromptDelivery::Stdin` the caller writes the prompt to the piped
+/// stdin after spawning. With `PromptDelivery::TempFile` stdin is redirected
+/// from a private file, which is returned so it outlives the spawn.
+/// Persistent sessions always read their messages from stdin.
+///
+/// # Errors
+///
+/// Returns `Error::StdinWriteFailed` if the prompt file cannot be written.
+pub fn attach(cmd: &mut Command, config: &AgentConfig, prompt: &str) -> Result<Option<TempFile>> {
+    if config.persistent {
+        return Ok(None);
+    }
+    match config.prompt_delivery {
+        PromptDelivery::Argv => Ok(None),
+        PromptDelivery::Stdin => {
+            cmd.stdin(Stdio::piped());
+            Ok(None)
+        }
+        PromptDelivery::TempFile => {
+            let (file, guard) =
+                write_file("prompt", prompt).map_err(|e| Error::StdinWriteFailed { source: e })?;
+            cmd.stdin(Stdio::from(file));
+            Ok(Some(guard))
+        }
+    }
+}
+
+/// Writes the whole prompt to `stdin`; dropping it afterwards closes it.
+///
+/// # Errors
+///
+/// Returns `Error::StdinWriteFailed` if the child stopped reading.
+pub fn write_prompt(stdin: &mut impl Write, prompt: &str) -> Result<()> {
+    stdin
+        .write_all(prompt.as_bytes())
+        .and_then(|()| stdin.flush())
+        .map_err(|e| Error::StdinWriteFailed { source: e })
+}
+
+/// Writes `contents` to a new file only the current user can read, rewound
+/// so it can be passed to the child as stdin.
+pub fn write_file(label: &str, contents: &str) -> io::Result<(File, TempFile)> {
+    let path = std::env::temp_dir().join(format!(
+        "agent-{label}-{}-{}",
+        std::process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    let guard = TempFile { path };
+    file.write_all(contents.as_bytes())?;
+    file.rewind()?;
+    Ok((file, guard))
+}
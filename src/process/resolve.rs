@@ -0,0 +1,50 @@
+//! Resolution of CLI binaries on PATH without spawning `which`.
+
+use crate::config::AgentConfig;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// Returns the absolute path of the binary `config` would run.
+///
+/// A configured binary path containing a separator is only checked; a bare
+/// name is looked up on the PATH the CLI will see, falling back to this
+/// process's PATH when the configured environment has none.
+pub fn resolve(config: &AgentConfig) -> Option<PathBuf> {
+    let path_var = config
+        .env_var("PATH")
+        .map(OsString::from)
+        .or_else(|| std::env::var_os("PATH"));
+    find_program(config.program(), path_var.as_deref())
+}
+
+/// Finds `program` in the directories of `path_var`.
+///
+/// Names containing a path separator are not looked up, only checked. An
+/// empty PATH entry stands for the current directory.
+pub fn find_program(program: &Path, path_var: Option<&OsStr>) -> Option<PathBuf> {
+    if program.components().count() > 1 {
+        return is_executable(program)
+            .then(|| std::path::absolute(program).ok())
+            .flatten();
+    }
+    std::env::split_paths(path_var?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+        .and_then(|found| std::path::absolute(found).ok())
+}
+
+/// Returns whether `path` is a regular file this process may execute.
+pub fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use nix::unistd::{access, AccessFlags};
+        metadata.is_file() && access(path, AccessFlags::X_OK).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
@@ -0,0 +1,45 @@
+//! Numbering of the events a CLI process produces.
+
+use crate::events::{AgentEvent, EventEnvelope, EventSource};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// The event counter shared by a process's reader threads or tasks.
+#[derive(Debug, Clone, Default)]
+pub struct Sequence(Arc<Mutex<u64>>);
+
+impl Sequence {
+    /// Numbers `event` and hands the envelope to `send`.
+    ///
+    /// The counter stays locked until `send` returns, so events reach the
+    /// channel in the order of their numbers.
+    pub fn queue<T>(
+        &self,
+        source: EventSource,
+        received_at: SystemTime,
+        event: AgentEvent,
+        send: impl FnOnce(EventEnvelope) -> T,
+    ) -> T {
+        let mut next = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let envelope = EventEnvelope {
+            seq: *next,
+            received_at,
+            source,
+            event,
+        };
+        *next += 1;
+        let result = send(envelope);
+        drop(next);
+        result
+    }
+
+    /// Numbers an event the runner emits itself, outside the channel.
+    pub fn envelope(&self, event: AgentEvent) -> EventEnvelope {
+        self.queue(
+            EventSource::Runner,
+            SystemTime::now(),
+            event,
+            std::convert::identity,
+        )
+    }
+}
@@ -0,0 +1,51 @@
+//! Process-group signalling for spawned CLIs.
+//!
+//! Each CLI runs in its own process group so that terminating a turn also
+//! reaches the tool subprocesses it started, which would otherwise keep the
+//! stdout pipe open.
+
+use std::process::Command;
+
+/// A termination signal, in escalation order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// `SIGINT`, the equivalent of pressing Ctrl-C.
+    Interrupt,
+    /// `SIGTERM`.
+    Terminate,
+    /// `SIGKILL`.
+    Kill,
+}
+
+/// Places the command in a new process group led by the child.
+pub fn isolate(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Sends `signal` to the process group led by `pid`.
+pub fn send_group(pid: u32, signal: Signal) {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{killpg, Signal as Native};
+        use nix::unistd::Pid;
+        let native = match signal {
+            Signal::Interrupt => Native::SIGINT,
+            Signal::Terminate => Native::SIGTERM,
+            Signal::Kill => Native::SIGKILL,
+        };
+        if let Ok(raw) = i32::try_from(pid) {
+            if raw <= 0 {
+                return;
+            }
+            let _ = killpg(Pid::from_raw(raw), native);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (pid, signal);
+}
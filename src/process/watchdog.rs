@@ -0,0 +1,113 @@
+//! Wall-clock and idle timeout enforcement for running turns.
+
+use super::control::TurnControl;
+use crate::config::AgentConfig;
+use crate::error::ErrorKind;
+use crate::events::AgentEvent;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Time limits applied to a single turn.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    total: Option<Duration>,
+    idle: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Reads the limits from the session configuration.
+    pub const fn from_config(config: &AgentConfig) -> Self {
+        Self {
+            total: config.timeout,
+            idle: config.idle_timeout,
+        }
+    }
+
+    /// Returns whether any limit is configured.
+    pub const fn is_enabled(&self) -> bool {
+        self.total.is_some() || self.idle.is_some()
+    }
+
+    /// Returns the idle limit, if configured.
+    #[cfg(feature = "tokio")]
+    pub const fn idle(&self) -> Option<Duration> {
+        self.idle
+    }
+
+    /// Returns the wall-clock limit, if configured.
+    #[cfg(feature = "tokio")]
+    pub const fn total(&self) -> Option<Duration> {
+        self.total
+    }
+
+    fn next_deadline(&self, started: Instant, last_line: Instant) -> Option<(Instant, Expired)> {
+        let total = self.total.map(|d| (started + d, Expired::Total(d)));
+        let idle = self.idle.map(|d| (last_line + d, Expired::Idle(d)));
+        match (total, idle) {
+            (Some(t), Some(i)) => Some(if i.0 < t.0 { i } else { t }),
+            (t, i) => t.or(i),
+        }
+    }
+}
+
+/// The limit that ended a turn.
+#[derive(Debug, Clone, Copy)]
+pub enum Expired {
+    /// The wall-clock limit was reached.
+    Total(Duration),
+    /// No stdout line arrived within the idle limit.
+    Idle(Duration),
+}
+
+impl Expired {
+    /// Returns the error event reporting this timeout.
+    pub fn event(self) -> AgentEvent {
+        let message = match self {
+            Self::Total(d) => format!("Turn exceeded timeout of {}ms", d.as_millis()),
+            Self::Idle(d) => format!("No output received for {}ms", d.as_millis()),
+        };
+        AgentEvent::Error {
+            kind: ErrorKind::Timeout,
+            message,
+        }
+    }
+}
+
+/// Watches stdout activity and kills the process once a limit expires.
+///
+/// Each message on `activity` marks a received stdout line. Limits only run
+/// while `control` reports a turn in progress and restart with each turn of
+/// a persistent process. Returns when the sender is dropped or after the
+/// child has been killed.
+pub fn watch(
+    control: &TurnControl,
+    activity: &Receiver<()>,
+    timeouts: Timeouts,
+) -> Option<Expired> {
+    let mut started = Instant::now();
+    let mut last_line = started;
+    loop {
+        let (deadline, expired) = timeouts.next_deadline(started, last_line)?;
+        match activity.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(()) => last_line = Instant::now(),
+            Err(RecvTimeoutError::Timeout) if Instant::now() >= deadline => {
+                match control.turn_started() {
+                    None => {
+                        started = Instant::now();
+                        last_line = started;
+                    }
+                    Some(turn) if turn > started => {
+                        started = turn;
+                        last_line = last_line.max(turn);
+                    }
+                    Some(_) => {
+                        control.kill();
+                        return Some(expired);
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
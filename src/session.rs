@@ -1,9 +1,12 @@
 //! Agent session management.
 
+use crate::auth;
 use crate::config::{AgentConfig, AgentKind};
 use crate::error::{Error, Result};
-use crate::events::AgentEvent;
-use crate::process::ProcessHandle;
+use crate::events::{AgentEvent, EventEnvelope};
+use crate::probe::{self, CliInfo};
+use crate::process::{check_supported, resolve_program, CancelHandle, ProcessHandle, Sequence};
+use std::io;
 use std::sync::mpsc::Receiver;
 
 /// A session with an agent CLI.
@@ -12,9 +15,13 @@ use std::sync::mpsc::Receiver;
 /// access to the event stream.
 pub struct AgentSession {
     config: AgentConfig,
+    receiver: Option<Receiver<EventEnvelope>>,
     process: Option<ProcessHandle>,
-    receiver: Option<Receiver<AgentEvent>>,
+    cancel: CancelHandle,
     session_id: Option<String>,
+    started: bool,
+    notice: Option<EventEnvelope>,
+    cli_info: Option<CliInfo>,
 }
 
 impl AgentSession {
@@ -22,22 +29,32 @@ impl AgentSession {
     ///
     /// # Errors
     ///
-    /// Returns an error if the CLI binary is not found, the API key is missing,
-    /// or the process fails to spawn.
-    pub fn spawn(config: AgentConfig, prompt: &str) -> Result<Self> {
-        Self::validate_environment(&config)?;
-        let (process, receiver) = ProcessHandle::spawn(&config, prompt)?;
+    /// Returns an error if the CLI binary is not found, no credentials are
+    /// found (see [`AuthCheck`](crate::AuthCheck)), or the process fails to
+    /// spawn.
+    pub fn spawn(mut config: AgentConfig, prompt: &str) -> Result<Self> {
+        let sequence = Sequence::default();
+        let mut cli_info = None;
+        let notice =
+            Self::validate_environment(&mut config, &mut cli_info)?.map(|e| sequence.envelope(e));
+        let (process, receiver) = ProcessHandle::spawn(&config, prompt, sequence)?;
         Ok(Self {
             config,
-            process: Some(process),
             receiver: Some(receiver),
+            cancel: CancelHandle::new(process.control()),
+            process: Some(process),
             session_id: None,
+            started: false,
+            notice,
+            cli_info,
         })
     }
 
     /// Returns an iterator over events from the agent.
     ///
-    /// This consumes the receiver, so it can only be called once.
+    /// This consumes the receiver, so it can only be called once per turn.
+    /// In a persistent session the iterator ends after the turn's
+    /// `TurnCompleted` event and can be requested again for the next turn.
     ///
     /// # Errors
     ///
@@ -45,32 +62,80 @@ impl AgentSession {
     pub fn events(&mut self) -> Result<EventIterator<'_>> {
         let receiver = self.receiver.take().ok_or(Error::ReceiverDisconnected)?;
         Ok(EventIterator {
-            receiver,
+            receiver: Some(receiver),
+            turn_done: false,
             session: self,
         })
     }
 
+    /// Returns an iterator over events with their sequence number, arrival
+    /// time and source.
+    ///
+    /// Behaves like [`events`](Self::events) otherwise and shares its
+    /// receiver, so only one of the two can be used per turn.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the receiver has already been consumed.
+    pub fn envelopes(&mut self) -> Result<EnvelopeIterator<'_>> {
+        self.events().map(EnvelopeIterator)
+    }
+
     /// Sends a follow-up message to continue the conversation.
     ///
-    /// This spawns a new process with the resume flag and session ID.
+    /// This spawns a new process that resumes the session captured from the
+    /// previous turn's events. A persistent session writes the message to the
+    /// running process instead.
     ///
     /// # Errors
     ///
     /// Returns an error if multi-turn is not supported, no session ID is
-    /// available, or the process fails to spawn.
+    /// available, the process fails to spawn, or a persistent process no
+    /// longer accepts input.
     pub fn send_input(&mut self, prompt: &str) -> Result<()> {
-        let session_id = self.session_id.clone().ok_or(Error::NoSessionId)?;
-        let config = AgentConfig {
+        if self.config.persistent {
+            let process = self
+                .process
+                .as_mut()
+                .ok_or_else(|| Error::StdinWriteFailed {
+                    source: io::Error::new(io::ErrorKind::BrokenPipe, "session process is gone"),
+                })?;
+            return process.send(prompt);
+        }
+        let resumable = self
+            .process
+            .as_ref()
+            .is_none_or(|process| process.control().is_resumable());
+        let session_id = resume_id(
+            &self.config,
+            self.session_id.as_ref(),
+            self.started,
+            resumable,
+        )?;
+        let mut config = AgentConfig {
             session_id: Some(session_id),
             ..self.config.clone()
         };
-        Self::validate_environment(&config)?;
-        let (process, receiver) = ProcessHandle::spawn(&config, prompt)?;
+        let sequence = Sequence::default();
+        self.notice = Self::validate_environment(&mut config, &mut self.cli_info)?
+            .map(|e| sequence.envelope(e));
+        let (process, receiver) = ProcessHandle::spawn(&config, prompt, sequence)?;
+        self.cancel.replace(process.control());
         self.process = Some(process);
         self.receiver = Some(receiver);
         Ok(())
     }
 
+    /// Returns a handle that can cancel the running turn from another thread.
+    ///
+    /// The handle stays valid across turns started with `send_input`. In a
+    /// persistent session cancelling ends the whole process, so later
+    /// `send_input` calls fail.
+    #[must_use]
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
     /// Returns the session ID if available.
     #[must_use]
     pub fn session_id(&self) -> Option<&str> {
@@ -79,58 +144,120 @@ impl AgentSession {
 
     /// Returns the agent kind for this session.
     #[must_use]
-    pub const fn kind(&self) -> AgentKind {
-        self.config.kind
+    pub const fn kind(&self) -> &AgentKind {
+        &self.config.kind
     }
 
-    /// Updates the session ID (called internally when discovered from events).
-    pub(crate) fn set_session_id(&mut self, id: String) {
-        self.session_id = Some(id);
+    /// Records a session start (called internally when discovered from events).
+    pub(crate) fn set_session_id(&mut self, id: Option<String>) {
+        self.started = true;
+        if id.is_some() {
+            self.session_id = id;
+        }
     }
 
-    fn validate_environment(config: &AgentConfig) -> Result<()> {
-        let binary = config.kind.binary_name();
-        if !Self::binary_exists(binary) {
-            return Err(Error::BinaryNotFound {
-                cli_name: binary.to_string(),
+    /// Checks that `config` can be spawned.
+    ///
+    /// The binary is resolved to an absolute path and pinned in
+    /// `config.binary_path`, so later turns keep running the same binary even
+    /// if PATH changes. A preflight probe is kept in `cli_info` and reused
+    /// while the binary stays the same. Returns a warning to emit before the
+    /// turn's events, if any.
+    pub(crate) fn validate_environment(
+        config: &mut AgentConfig,
+        cli_info: &mut Option<CliInfo>,
+    ) -> Result<Option<AgentEvent>> {
+        if config.persistent && !matches!(config.kind, AgentKind::Claude) {
+            return Err(Error::MultiTurnNotSupported {
+                cli_kind: config.kind.display_name().to_string(),
             });
         }
-        let env_var = config.kind.api_key_env_var();
-        if std::env::var(env_var).is_err() {
-            return Err(Error::ApiKeyMissing {
-                env_var: env_var.to_string(),
-            });
+        check_supported(config)?;
+        let program = resolve_program(config).ok_or_else(|| Error::BinaryNotFound {
+            cli_name: config.program().display().to_string(),
+        })?;
+        config.binary_path = Some(program.clone());
+        if config.preflight {
+            probe::preflight(config, program, cli_info)?;
         }
-        Ok(())
+        auth::check(config)
     }
+}
 
-    fn binary_exists(name: &str) -> bool {
-        std::process::Command::new("which")
-            .arg(name)
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
+/// Returns the session ID a follow-up turn should resume.
+///
+/// A session that started without reporting an ID, or whose CLI spoke a
+/// protocol that cannot resume, is reported as such instead of silently
+/// starting over.
+pub fn resume_id(
+    config: &AgentConfig,
+    session_id: Option<&String>,
+    started: bool,
+    resumable: bool,
+) -> Result<String> {
+    match session_id {
+        Some(id) if resumable => Ok(id.clone()),
+        None if !started => Err(Error::NoSessionId),
+        _ => Err(Error::MultiTurnNotSupported {
+            cli_kind: config.kind.display_name().to_string(),
+        }),
     }
 }
 
 /// An iterator over events from an agent session.
 pub struct EventIterator<'a> {
-    receiver: Receiver<AgentEvent>,
+    receiver: Option<Receiver<EventEnvelope>>,
+    turn_done: bool,
     session: &'a mut AgentSession,
 }
 
+impl EventIterator<'_> {
+    fn next_envelope(&mut self) -> Option<EventEnvelope> {
+        if self.turn_done {
+            return None;
+        }
+        if let Some(notice) = self.session.notice.take() {
+            return Some(notice);
+        }
+        match self.receiver.as_ref()?.recv() {
+            Ok(envelope) => {
+                match envelope.event {
+                    AgentEvent::SessionStarted { ref session_id, .. } => {
+                        self.session.set_session_id(session_id.clone());
+                    }
+                    AgentEvent::TurnCompleted => self.turn_done = true,
+                    _ => {}
+                }
+                Some(envelope)
+            }
+            Err(_) => None,
+        }
+    }
+}
+
 impl Iterator for EventIterator<'_> {
     type Item = AgentEvent;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.receiver.recv() {
-            Ok(event) => {
-                if let AgentEvent::SessionStarted { session_id: Some(ref id) } = event {
-                    self.session.set_session_id(id.clone());
-                }
-                Some(event)
-            }
-            Err(_) => None,
+        self.next_envelope().map(|envelope| envelope.event)
+    }
+}
+
+/// An iterator over events from an agent session with their envelopes.
+pub struct EnvelopeIterator<'a>(EventIterator<'a>);
+
+impl Iterator for EnvelopeIterator<'_> {
+    type Item = EventEnvelope;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_envelope()
+    }
+}
+
+impl Drop for EventIterator<'_> {
+    fn drop(&mut self) {
+        if self.session.config.persistent {
+            self.session.receiver = self.receiver.take();
         }
     }
 }
@@ -1,108 +1,202 @@
 //! Stream reading and parsing for agent CLI output.
 
-use crate::config::AgentKind;
+use crate::config::{AgentConfig, AgentKind};
 use crate::error::ErrorKind;
-use crate::events::AgentEvent;
-use crate::parsers;
-use crate::process::SyncSenderWrapper;
+use crate::events::{AgentEvent, EventSource};
+use crate::parsers::{self, claude::PartialMessages};
+use crate::process::{CompletionRecord, EventSender, TurnControl};
 use std::io::{BufRead, BufReader, Read};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Reads and parses the stdout stream from an agent CLI.
 pub struct StreamReader<R: Read> {
     reader: BufReader<R>,
-    kind: AgentKind,
+    parser: LineParser,
     debug: bool,
+    activity: Option<Sender<()>>,
+    completion: CompletionRecord,
+    control: Arc<TurnControl>,
 }
 
 impl<R: Read> StreamReader<R> {
-    /// Creates a new stream reader.
-    pub fn new(reader: R, kind: AgentKind, debug: bool) -> Self {
+    /// Creates a new stream reader for the process tracked by `control`.
+    pub fn new(reader: R, config: &AgentConfig, control: Arc<TurnControl>) -> Self {
         Self {
             reader: BufReader::new(reader),
-            kind,
-            debug,
+            parser: LineParser::new(config),
+            debug: config.debug,
+            activity: None,
+            completion: CompletionRecord::default(),
+            control,
         }
     }
 
+    /// Notifies `activity` whenever a line is read, for idle tracking.
+    #[must_use]
+    pub fn with_activity(mut self, activity: Option<Sender<()>>) -> Self {
+        self.activity = activity;
+        self
+    }
+
+    /// Treats completion records as turn boundaries of a persistent process.
+    ///
+    /// Each record is forwarded as `TurnCompleted` and ends the current turn.
+    #[must_use]
+    pub const fn with_turns(mut self) -> Self {
+        self.completion = CompletionRecord::persistent();
+        self
+    }
+
     /// Reads the stream and sends events to the channel.
-    pub fn read_to_channel(mut self, sender: &SyncSenderWrapper) {
+    ///
+    /// Completion events from the parser are withheld and returned as a
+    /// record so the caller can report the process exit authoritatively.
+    pub fn read_to_channel(mut self, sender: &EventSender) -> CompletionRecord {
         let mut line = String::new();
         loop {
             line.clear();
             match self.reader.read_line(&mut line) {
                 Ok(0) => break,
                 Ok(_) => {
+                    let received_at = SystemTime::now();
+                    if let Some(ref activity) = self.activity {
+                        let _ = activity.send(());
+                    }
                     let trimmed = line.trim();
                     if trimmed.is_empty() {
                         continue;
                     }
-                    self.parse_and_send(trimmed, sender);
+                    self.parse_and_send(trimmed, received_at, sender);
                 }
                 Err(e) => {
                     if self.debug {
-                        let _ = sender.send(AgentEvent::Error {
+                        let event = AgentEvent::Error {
                             kind: ErrorKind::Debug,
                             message: format!("Read error: {e}"),
-                        });
+                        };
+                        let _ = sender.send(EventSource::Stdout, SystemTime::now(), event);
                     }
                     break;
                 }
             }
         }
+        self.completion
     }
 
-    fn parse_and_send(&self, line: &str, sender: &SyncSenderWrapper) {
-        match serde_json::from_str::<serde_json::Value>(line) {
-            Ok(json) => {
-                let events = self.parse_json(&json);
-                for event in events {
-                    if sender.send(event).is_err() {
-                        return;
-                    }
-                }
+    fn parse_and_send(&mut self, line: &str, received_at: SystemTime, sender: &EventSender) {
+        let events = self.parser.parse(line);
+        if !self.parser.is_resumable() {
+            self.control.mark_unresumable();
+        }
+        for event in events {
+            let Some(event) = self.completion.observe(event) else {
+                continue;
+            };
+            if matches!(event, AgentEvent::TurnCompleted) {
+                self.control.end_turn();
             }
+            if sender
+                .send(EventSource::Stdout, received_at, event)
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Parses non-empty stdout lines into agent events.
+///
+/// Shared by the blocking and async runners so both emit identical events.
+/// Keeps the state of content blocks streamed across lines.
+pub struct LineParser {
+    kind: AgentKind,
+    debug: bool,
+    partial: PartialMessages,
+    resumable: bool,
+    mcp_servers: Vec<String>,
+}
+
+impl LineParser {
+    /// Creates a parser for the stdout of one process started with `config`.
+    pub fn new(config: &AgentConfig) -> Self {
+        Self {
+            kind: config.kind.clone(),
+            debug: config.debug,
+            partial: PartialMessages::default(),
+            resumable: true,
+            mcp_servers: config.mcp_servers.iter().map(|s| s.name.clone()).collect(),
+        }
+    }
+
+    /// Returns whether the lines parsed so far allow resuming the session.
+    pub const fn is_resumable(&self) -> bool {
+        self.resumable
+    }
+
+    /// Parses a single non-empty stdout line.
+    pub fn parse(&mut self, line: &str) -> Vec<AgentEvent> {
+        match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(json) => self.parse_json(&json),
             Err(e) => {
+                let mut events = Vec::new();
                 if self.debug {
-                    let _ = sender.send(AgentEvent::Error {
+                    events.push(AgentEvent::Error {
                         kind: ErrorKind::Debug,
                         message: format!("JSON parse debug: {e}"),
                     });
                 }
-                let _ = sender.send(AgentEvent::Error {
+                events.push(AgentEvent::Error {
                     kind: ErrorKind::UnparsedOutput,
                     message: line.to_string(),
                 });
+                events
             }
         }
     }
 
-    fn parse_json(&self, json: &serde_json::Value) -> Vec<AgentEvent> {
+    fn parse_json(&mut self, json: &serde_json::Value) -> Vec<AgentEvent> {
         match self.kind {
-            AgentKind::Claude => parsers::claude::parse(json),
-            AgentKind::Codex => parsers::codex::parse(json),
-            AgentKind::Gemini => parsers::gemini::parse(json),
+            AgentKind::Claude => parsers::claude::parse(json, &mut self.partial),
+            AgentKind::Codex => {
+                if parsers::codex::is_legacy(json) {
+                    self.resumable = false;
+                }
+                parsers::codex::parse(json)
+            }
+            AgentKind::Gemini => parsers::gemini::parse(json, &self.mcp_servers),
+            AgentKind::Custom(ref backend) => backend.parse(json),
         }
     }
 }
 
+/// Wraps a stderr line in an error event, skipping blank lines.
+pub fn stderr_event(text: String) -> Option<AgentEvent> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    Some(AgentEvent::Error {
+        kind: ErrorKind::Stderr,
+        message: text,
+    })
+}
+
 /// Reads stderr and sends error events to the channel.
-pub fn read_stderr<S: Read>(reader: S, sender: &SyncSenderWrapper) {
+pub fn read_stderr<S: Read>(reader: S, sender: &EventSender) {
     let buf_reader = BufReader::new(reader);
     for line in buf_reader.lines() {
-        match line {
-            Ok(text) if !text.trim().is_empty() => {
-                if sender
-                    .send(AgentEvent::Error {
-                        kind: ErrorKind::Stderr,
-                        message: text,
-                    })
-                    .is_err()
-                {
-                    return;
-                }
+        let Ok(text) = line else {
+            break;
+        };
+        if let Some(event) = stderr_event(text) {
+            if sender
+                .send(EventSource::Stderr, SystemTime::now(), event)
+                .is_err()
+            {
+                return;
             }
-            Ok(_) => {}
-            Err(_) => break,
         }
     }
 }
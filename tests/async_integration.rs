@@ -0,0 +1,181 @@
+//! Integration tests for the Tokio-based async session API.
+
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use agent_cli_runner::{
+    AgentConfig, AgentEvent, AgentKind, AgentSession, AsyncAgentSession, Error, EventEnvelope,
+    EventSource, PromptDelivery, SessionMetadata,
+};
+use futures_util::StreamExt;
+
+const CLAUDE_LINES: &[&str] = &[
+    r#"{"type":"system","subtype":"init","session_id":"async-session-1"}"#,
+    r#"{"type":"assistant","message":{"content":[{"type":"text","text":"hello"}]}}"#,
+    r#"{"type":"result","usage":{"input_tokens":3,"output_tokens":1}}"#,
+];
+
+#[tokio::test]
+async fn test_async_session_streams_events() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Claude);
+    let mut session = AsyncAgentSession::spawn(config, &common::print_lines(CLAUDE_LINES)).await?;
+    let events: Vec<AgentEvent> = session.events()?.collect().await;
+    assert_eq!(
+        events.first(),
+        Some(&AgentEvent::SessionStarted {
+            session_id: Some("async-session-1".to_string()),
+            metadata: SessionMetadata::default(),
+        })
+    );
+    assert!(events.contains(&AgentEvent::Text {
+        content: "hello".to_string(),
+        is_partial: false,
+    }));
+    assert_eq!(session.session_id(), Some("async-session-1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_events_match_blocking_events() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let prompt = common::print_lines(CLAUDE_LINES);
+    let mut async_session =
+        AsyncAgentSession::spawn(AgentConfig::new(AgentKind::Claude), &prompt).await?;
+    let async_events: Vec<AgentEvent> = async_session.events()?.collect().await;
+    let blocking_events = tokio::task::spawn_blocking(move || {
+        let mut session = AgentSession::spawn(AgentConfig::new(AgentKind::Claude), &prompt)?;
+        let events: Vec<AgentEvent> = session.events()?.collect();
+        Ok::<_, agent_cli_runner::Error>(events)
+    })
+    .await
+    .map_err(|_| agent_cli_runner::Error::ReceiverDisconnected)??;
+    assert_eq!(async_events, blocking_events);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_rejects_persistent_sessions() {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Claude).with_persistent_session();
+    assert!(matches!(
+        AsyncAgentSession::spawn(config, "true").await,
+        Err(Error::UnsupportedOption { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_async_events_only_once() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let mut session = AsyncAgentSession::spawn(AgentConfig::new(AgentKind::Codex), "true").await?;
+    let _ = session.events()?;
+    assert!(session.events().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_idle_timeout() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Claude)
+        .with_idle_timeout(std::time::Duration::from_millis(300));
+    let mut session = AsyncAgentSession::spawn(config, "sleep 30").await?;
+    let events: Vec<AgentEvent> = session.events()?.collect().await;
+    assert!(matches!(
+        events.first(),
+        Some(AgentEvent::Error {
+            kind: agent_cli_runner::ErrorKind::Timeout,
+            ..
+        })
+    ));
+    assert_eq!(
+        events.last(),
+        Some(&AgentEvent::SessionCompleted {
+            exit_code: None,
+            signal: Some(9),
+            completion_record: false,
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_cancel_handle() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let mut session =
+        AsyncAgentSession::spawn(AgentConfig::new(AgentKind::Codex), "sleep 30").await?;
+    let handle = session.cancel_handle();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        handle.cancel();
+    });
+    let events: Vec<AgentEvent> = session.events()?.collect().await;
+    assert_eq!(events.last(), Some(&AgentEvent::Cancelled));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_prompt_over_stdin() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Claude).with_prompt_delivery(PromptDelivery::Stdin);
+    let mut session = AsyncAgentSession::spawn(config, &common::print_lines(CLAUDE_LINES)).await?;
+    let events: Vec<AgentEvent> = session.events()?.collect().await;
+    assert!(events.contains(&AgentEvent::Text {
+        content: "hello".to_string(),
+        is_partial: false,
+    }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_envelopes() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let prompt = format!(
+        "{}; echo 'warning: slow' >&2",
+        common::print_lines(CLAUDE_LINES)
+    );
+    let mut session =
+        AsyncAgentSession::spawn(AgentConfig::new(AgentKind::Claude), &prompt).await?;
+    let envelopes: Vec<EventEnvelope> = session.envelopes()?.collect().await;
+    let seqs: Vec<u64> = envelopes.iter().map(|envelope| envelope.seq).collect();
+    assert_eq!(seqs, (0..5).collect::<Vec<u64>>());
+    let stderr: Vec<&AgentEvent> = envelopes
+        .iter()
+        .filter(|envelope| envelope.source == EventSource::Stderr)
+        .map(|envelope| &envelope.event)
+        .collect();
+    assert_eq!(
+        stderr,
+        [&AgentEvent::Error {
+            kind: agent_cli_runner::ErrorKind::Stderr,
+            message: "warning: slow".to_string(),
+        }]
+    );
+    assert_eq!(
+        envelopes.last().map(|envelope| envelope.source),
+        Some(EventSource::Runner)
+    );
+    assert_eq!(session.session_id(), Some("async-session-1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_preflight_across_turns() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let path = common::fake_bin_dir().join("versions").join("codex-async");
+    common::install_fake_cli_at(&path);
+    let script = "#!/bin/sh\ncase \"$1 $2\" in\n'--version ') echo 'codex-cli 0.46.0' ;;\n'exec --help') printf '%s\\n' '  resume' '--json' ;;\n*) echo '{\"type\":\"thread.started\",\"thread_id\":\"t1\"}' ;;\nesac\n";
+    let _ = std::fs::write(&path, script);
+    let config = AgentConfig::new(AgentKind::Codex)
+        .with_binary_path(path)
+        .with_preflight();
+    let mut session = AsyncAgentSession::spawn(config, "true").await?;
+    let _ = session.events()?.count().await;
+    session.send_input("again").await?;
+    let events: Vec<AgentEvent> = session.events()?.collect().await;
+    assert!(events.contains(&AgentEvent::SessionStarted {
+        session_id: Some("t1".to_string()),
+        metadata: SessionMetadata::default(),
+    }));
+    Ok(())
+}
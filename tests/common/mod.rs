@@ -0,0 +1,85 @@
+//! Shared helpers for integration tests that drive fake agent CLIs.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::Once;
+
+static INSTALL: Once = Once::new();
+
+const FAKE_CLI: &str = "#!/bin/sh\nfor prompt; do :; done\ncase \"$prompt\" in -|stream-json) prompt=$(cat) ;; esac\neval \"$prompt\"\n";
+
+/// Installs fake `claude`, `codex` and `gemini` binaries at the front of PATH.
+///
+/// Each fake evaluates its last argument as a shell script, so the prompt
+/// passed to a session decides what the fake CLI prints. When the last
+/// argument is an option value instead of a prompt, the script is read from
+/// stdin.
+pub fn install_fake_clis() {
+    INSTALL.call_once(|| {
+        let dir = fake_bin_dir();
+        let _ = fs::create_dir_all(&dir);
+        for name in ["claude", "codex", "gemini"] {
+            let path = dir.join(name);
+            let _ = fs::write(&path, FAKE_CLI);
+            let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o755));
+        }
+        let path = std::env::var("PATH").unwrap_or_default();
+        std::env::set_var("PATH", format!("{}:{path}", dir.display()));
+        for var in ["ANTHROPIC_API_KEY", "OPENAI_API_KEY", "GOOGLE_API_KEY"] {
+            if std::env::var(var).is_err() {
+                std::env::set_var(var, "test-key");
+            }
+        }
+    });
+}
+
+/// Writes a fake CLI binary to `path`, outside of PATH.
+pub fn install_fake_cli_at(path: &std::path::Path) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, FAKE_CLI);
+    let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o755));
+}
+
+/// Returns the directory holding the fake CLI binaries.
+pub fn fake_bin_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("fake-cli-{}", std::process::id()))
+}
+
+/// Builds a fake CLI prompt that prints each line to stdout.
+pub fn print_lines(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("printf '%s\\n' '{line}'"))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Builds a fake CLI prompt that replays the stdout lines of a fixture log.
+///
+/// Lines are taken from `[stdout]` entries; in multi-agent logs only the
+/// entries tagged with `agent` are kept.
+pub fn replay_fixture(fixture: &str, agent: &str) -> String {
+    let source = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(fixture);
+    let content = fs::read_to_string(&source).unwrap_or_default();
+    let tagged = format!("[{agent}][stdout] ");
+    let lines: Vec<&str> = content
+        .lines()
+        .filter_map(|line| {
+            line.split_once(&tagged)
+                .or_else(|| line.strip_prefix("[stdout] ").map(|rest| ("", rest)))
+                .map(|(_, payload)| payload)
+        })
+        .collect();
+    let name = fixture.replace('/', "_");
+    let target = fake_bin_dir().join(format!("{name}.{agent}.jsonl"));
+    let _ = fs::create_dir_all(fake_bin_dir());
+    let _ = fs::write(&target, lines.join("\n") + "\n");
+    format!("cat '{}'", target.display())
+}
@@ -0,0 +1,499 @@
+//! Integration tests for configuration applied to the spawned CLI process.
+
+mod common;
+
+use agent_cli_runner::{
+    AgentConfig, AgentEvent, AgentKind, AgentSession, Error, ErrorKind, McpServer, PermissionMode,
+    PromptDelivery,
+};
+use std::time::Duration;
+
+/// Builds a Gemini prompt that reports the value of each variable.
+fn print_env(vars: &[&str]) -> String {
+    vars.iter()
+        .map(|var| format!(r#"printf '{{"type":"text","text":"%s"}}\n' "${{{var}-unset}}""#))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn run_texts(config: AgentConfig, prompt: &str) -> agent_cli_runner::Result<Vec<String>> {
+    common::install_fake_clis();
+    let mut session = AgentSession::spawn(config, prompt)?;
+    let texts = session
+        .events()?
+        .filter_map(|e| match e {
+            AgentEvent::Text { content, .. } => Some(content),
+            _ => None,
+        })
+        .collect();
+    Ok(texts)
+}
+
+#[test]
+fn test_env_overrides_and_removal() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    std::env::set_var("AGENT_TEST_INHERITED", "parent");
+    std::env::set_var("AGENT_TEST_REMOVED", "parent");
+    let config = AgentConfig::new(AgentKind::Gemini)
+        .with_env("AGENT_TEST_BASE_URL", "http://127.0.0.1:8080")
+        .with_env_remove("AGENT_TEST_REMOVED");
+    let texts = run_texts(
+        config,
+        &print_env(&[
+            "AGENT_TEST_BASE_URL",
+            "AGENT_TEST_INHERITED",
+            "AGENT_TEST_REMOVED",
+        ]),
+    )?;
+    assert_eq!(texts, vec!["http://127.0.0.1:8080", "parent", "unset"]);
+    Ok(())
+}
+
+#[test]
+fn test_clean_env_keeps_only_allowlist() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    std::env::set_var("AGENT_TEST_SECRET", "leaked");
+    let config = AgentConfig::new(AgentKind::Gemini)
+        .with_clean_env(["PATH", "GOOGLE_API_KEY"])
+        .with_env("AGENT_TEST_TENANT", "acme");
+    let texts = run_texts(
+        config,
+        &print_env(&["AGENT_TEST_SECRET", "AGENT_TEST_TENANT", "GOOGLE_API_KEY"]),
+    )?;
+    assert_eq!(texts, vec!["unset", "acme", "test-key"]);
+    Ok(())
+}
+
+#[test]
+fn test_api_key_check_uses_configured_env() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let missing = AgentConfig::new(AgentKind::Gemini).with_clean_env(["PATH"]);
+    assert!(matches!(
+        AgentSession::spawn(missing, "true"),
+        Err(Error::ApiKeyMissing { .. })
+    ));
+    let removed = AgentConfig::new(AgentKind::Gemini)
+        .with_env_remove("GOOGLE_API_KEY")
+        .with_env_remove("GEMINI_API_KEY")
+        .with_env("HOME", env!("CARGO_TARGET_TMPDIR"));
+    assert!(matches!(
+        AgentSession::spawn(removed, "true"),
+        Err(Error::ApiKeyMissing { .. })
+    ));
+    let tenant = AgentConfig::new(AgentKind::Gemini)
+        .with_clean_env(["PATH"])
+        .with_env("GOOGLE_API_KEY", "tenant-key");
+    let texts = run_texts(tenant, &print_env(&["GOOGLE_API_KEY"]))?;
+    assert_eq!(texts, vec!["tenant-key"]);
+    Ok(())
+}
+
+#[test]
+fn test_cloud_provider_flags_need_their_settings() {
+    common::install_fake_clis();
+    for (kind, env, accepted) in [
+        (
+            AgentKind::Claude,
+            &[
+                ("CLAUDE_CODE_USE_BEDROCK", "0"),
+                ("AWS_REGION", "us-east-1"),
+            ][..],
+            false,
+        ),
+        (
+            AgentKind::Claude,
+            &[("CLAUDE_CODE_USE_BEDROCK", "1")],
+            false,
+        ),
+        (
+            AgentKind::Claude,
+            &[
+                ("CLAUDE_CODE_USE_BEDROCK", "1"),
+                ("AWS_REGION", "us-east-1"),
+            ],
+            true,
+        ),
+        (
+            AgentKind::Gemini,
+            &[("GOOGLE_APPLICATION_CREDENTIALS", "/sa.json")],
+            false,
+        ),
+        (
+            AgentKind::Gemini,
+            &[
+                ("GOOGLE_GENAI_USE_VERTEXAI", "true"),
+                ("GOOGLE_APPLICATION_CREDENTIALS", "/sa.json"),
+            ],
+            true,
+        ),
+    ] {
+        let config = env.iter().fold(
+            AgentConfig::new(kind.clone()).with_clean_env(["PATH"]),
+            |config, (key, value)| config.with_env(*key, *value),
+        );
+        let result = AgentSession::spawn(config, "true");
+        assert_eq!(result.is_ok(), accepted, "{kind:?} {env:?}");
+    }
+}
+
+#[test]
+fn test_multi_value_extra_args_leave_prompt_alone() -> agent_cli_runner::Result<()> {
+    for kind in [AgentKind::Claude, AgentKind::Codex, AgentKind::Gemini] {
+        let config = AgentConfig::new(kind.clone())
+            .with_extra_args(["--add-dir", "/x"])
+            .with_session_id("s-1");
+        let args = cli_args(config)?;
+        let after = args.iter().skip_while(|arg| *arg != "/x").nth(1);
+        assert!(
+            after.is_some_and(|arg| arg.starts_with('-')),
+            "{kind:?} {args:?}"
+        );
+        assert!(args.contains(&"s-1".to_string()), "{kind:?} {args:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_binary_path_runs_pinned_build() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let pinned = common::fake_bin_dir().join("pinned").join("gemini-0.9.1");
+    common::install_fake_cli_at(&pinned);
+    let config = AgentConfig::new(AgentKind::Gemini).with_binary_path(pinned.clone());
+    assert_eq!(config.program(), pinned.as_path());
+    let texts = run_texts(
+        config,
+        &common::print_lines(&[r#"{"type":"text","text":"pinned"}"#]),
+    )?;
+    assert_eq!(texts, vec!["pinned"]);
+    Ok(())
+}
+
+#[test]
+fn test_binary_path_is_validated() {
+    common::install_fake_clis();
+    let missing = common::fake_bin_dir().join("pinned").join("missing-claude");
+    let config = AgentConfig::new(AgentKind::Claude).with_binary_path(missing);
+    assert!(matches!(
+        AgentSession::spawn(config, "true"),
+        Err(Error::BinaryNotFound { cli_name }) if cli_name.ends_with("missing-claude")
+    ));
+
+    let not_executable = common::fake_bin_dir().join("pinned").join("README");
+    let _ = std::fs::create_dir_all(common::fake_bin_dir().join("pinned"));
+    let _ = std::fs::write(&not_executable, "not a binary");
+    let config = AgentConfig::new(AgentKind::Claude).with_binary_path(not_executable);
+    assert!(matches!(
+        AgentSession::spawn(config, "true"),
+        Err(Error::BinaryNotFound { .. })
+    ));
+}
+
+/// Returns the arguments the CLI was started with, without the prompt.
+fn cli_args(config: AgentConfig) -> agent_cli_runner::Result<Vec<String>> {
+    common::install_fake_clis();
+    let mut session = AgentSession::spawn(config, r#"for arg; do printf 'arg:%s\n' "$arg"; done"#)?;
+    let mut args: Vec<String> = session
+        .events()?
+        .filter_map(|e| match e {
+            AgentEvent::Error {
+                kind: ErrorKind::UnparsedOutput,
+                message,
+            } => message.strip_prefix("arg:").map(String::from),
+            _ => None,
+        })
+        .collect();
+    args.pop();
+    Ok(args)
+}
+
+fn restricted(kind: AgentKind) -> AgentConfig {
+    AgentConfig::new(kind)
+        .with_allowed_tools(["Read", "Grep"])
+        .with_disallowed_tools(["Bash"])
+        .with_append_system_prompt("Be terse")
+        .with_max_turns(3)
+}
+
+#[test]
+fn test_claude_translates_restrictions() -> agent_cli_runner::Result<()> {
+    let args = cli_args(restricted(AgentKind::Claude))?;
+    assert_eq!(
+        args,
+        [
+            "--print",
+            "--allowedTools",
+            "Read,Grep",
+            "--disallowedTools",
+            "Bash",
+            "--output-format",
+            "stream-json",
+            "--append-system-prompt",
+            "Be terse",
+            "--max-turns",
+            "3",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_codex_translates_system_prompt() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Codex).with_append_system_prompt("Say \"hi\"");
+    let args = cli_args(config)?;
+    assert_eq!(
+        args,
+        [
+            "exec",
+            "--json",
+            "-c",
+            r#"developer_instructions="Say \"hi\"""#
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_gemini_translates_allowed_tools() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Gemini).with_allowed_tools(["read_file", "glob"]);
+    let args = cli_args(config)?;
+    assert_eq!(
+        args,
+        ["--allowed-tools", "read_file,glob", "-o", "stream-json"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_restrictions_are_rejected() {
+    common::install_fake_clis();
+    for (config, option) in [
+        (restricted(AgentKind::Codex), "allowed tools"),
+        (
+            AgentConfig::new(AgentKind::Codex).with_max_turns(2),
+            "max turns",
+        ),
+        (restricted(AgentKind::Gemini), "appended system prompts"),
+        (
+            AgentConfig::new(AgentKind::Gemini).with_disallowed_tools(["run_shell_command"]),
+            "disallowed tools",
+        ),
+    ] {
+        let kind = config.kind.display_name().to_string();
+        let err = AgentSession::spawn(config, "true").err();
+        assert!(
+            matches!(
+                err,
+                Some(Error::UnsupportedOption { ref cli_kind, option: ref got })
+                    if *cli_kind == kind && got == option
+            ),
+            "unexpected result for {kind}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn test_permission_modes_map_to_native_flags() -> agent_cli_runner::Result<()> {
+    for (kind, mode, expected) in [
+        (
+            AgentKind::Claude,
+            PermissionMode::ReadOnly,
+            &["--permission-mode", "plan"][..],
+        ),
+        (
+            AgentKind::Claude,
+            PermissionMode::AcceptEdits,
+            &["--permission-mode", "acceptEdits"],
+        ),
+        (
+            AgentKind::Codex,
+            PermissionMode::WorkspaceWrite,
+            &["--sandbox", "workspace-write"],
+        ),
+        (AgentKind::Codex, PermissionMode::FullAuto, &["--full-auto"]),
+        (
+            AgentKind::Gemini,
+            PermissionMode::AcceptEdits,
+            &["--approval-mode", "auto_edit"],
+        ),
+        (
+            AgentKind::Gemini,
+            PermissionMode::Bypass,
+            &["--approval-mode", "yolo"],
+        ),
+    ] {
+        let config = AgentConfig::new(kind.clone()).with_permission_mode(mode);
+        let args = cli_args(config)?;
+        assert!(
+            args.windows(expected.len()).any(|w| w == expected),
+            "{kind:?} {mode:?}: {args:?}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_permission_mode_overrides_skip_permissions() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Codex)
+        .with_skip_permissions()
+        .with_permission_mode(PermissionMode::ReadOnly);
+    let args = cli_args(config)?;
+    assert_eq!(args, ["exec", "--json", "--sandbox", "read-only"]);
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_permission_mode_is_rejected() {
+    common::install_fake_clis();
+    for (kind, mode) in [
+        (AgentKind::Claude, PermissionMode::FullAuto),
+        (AgentKind::Codex, PermissionMode::AcceptEdits),
+        (AgentKind::Gemini, PermissionMode::ReadOnly),
+    ] {
+        let config = AgentConfig::new(kind).with_permission_mode(mode);
+        let err = AgentSession::spawn(config, "true").err();
+        let expected = format!("the {mode:?} permission mode");
+        assert!(
+            matches!(err, Some(Error::UnsupportedOption { ref option, .. }) if *option == expected),
+            "unexpected result for {mode:?}: {err:?}"
+        );
+    }
+}
+
+fn with_docs_servers(kind: AgentKind) -> AgentConfig {
+    AgentConfig::new(kind)
+        .with_mcp_server(
+            McpServer::stdio("docs", "docs-mcp")
+                .with_args(["--index", "api"])
+                .with_env("DOCS_TOKEN", "secret"),
+        )
+        .with_mcp_server(McpServer::url("tickets", "https://mcp.example.com/tickets"))
+}
+
+#[test]
+fn test_claude_receives_mcp_config() -> agent_cli_runner::Result<()> {
+    let args = cli_args(with_docs_servers(AgentKind::Claude))?;
+    assert!(args.contains(&"--mcp-config".to_string()));
+    assert!(!args.iter().any(|a| a.contains("secret")), "{args:?}");
+    let mut session = AgentSession::spawn(
+        with_docs_servers(AgentKind::Claude),
+        r#"while [ "$1" != --mcp-config ]; do shift; done; printf 'config:%s\n' "$(cat "$2")""#,
+    )?;
+    let config: Vec<String> = session
+        .events()?
+        .filter_map(|e| match e {
+            AgentEvent::Error { message, .. } => message.strip_prefix("config:").map(String::from),
+            _ => None,
+        })
+        .collect();
+    let config: serde_json::Value = serde_json::from_str(&config.concat()).unwrap_or_default();
+    assert_eq!(
+        config,
+        serde_json::json!({"mcpServers": {
+            "docs": {
+                "type": "stdio",
+                "command": "docs-mcp",
+                "args": ["--index", "api"],
+                "env": {"DOCS_TOKEN": "secret"},
+            },
+            "tickets": {"type": "http", "url": "https://mcp.example.com/tickets"},
+        }})
+    );
+    Ok(())
+}
+
+#[test]
+fn test_codex_receives_mcp_overrides() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Codex)
+        .with_mcp_server(McpServer::stdio("docs", "docs-mcp").with_args(["--index", "api"]))
+        .with_mcp_server(McpServer::url("tickets", "https://mcp.example.com/tickets"));
+    let args = cli_args(config)?;
+    assert_eq!(
+        args,
+        [
+            "exec",
+            "--json",
+            "-c",
+            r#"mcp_servers.docs.command="docs-mcp""#,
+            "-c",
+            r#"mcp_servers.docs.args=["--index","api"]"#,
+            "-c",
+            r#"mcp_servers.tickets.url="https://mcp.example.com/tickets""#,
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_codex_rejects_mcp_server_env() {
+    common::install_fake_clis();
+    let err = AgentSession::spawn(with_docs_servers(AgentKind::Codex), "true").err();
+    assert!(
+        matches!(err, Some(Error::UnsupportedOption { ref option, .. }) if option.contains("environment")),
+        "unexpected result: {err:?}"
+    );
+}
+
+#[test]
+fn test_gemini_receives_mcp_settings() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config = with_docs_servers(AgentKind::Gemini);
+    let mut session = AgentSession::spawn(
+        config,
+        r#"printf 'settings:%s\n' "$(cat "$GEMINI_CLI_SYSTEM_SETTINGS_PATH")""#,
+    )?;
+    let settings: Vec<String> = session
+        .events()?
+        .filter_map(|e| match e {
+            AgentEvent::Error { message, .. } => {
+                message.strip_prefix("settings:").map(String::from)
+            }
+            _ => None,
+        })
+        .collect();
+    let settings: serde_json::Value = serde_json::from_str(&settings.concat()).unwrap_or_default();
+    assert_eq!(
+        settings,
+        serde_json::json!({"mcpServers": {
+            "docs": {
+                "command": "docs-mcp",
+                "args": ["--index", "api"],
+                "env": {"DOCS_TOKEN": "secret"},
+            },
+            "tickets": {"httpUrl": "https://mcp.example.com/tickets"},
+        }})
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_mcp_server_name_is_rejected() {
+    common::install_fake_clis();
+    let config =
+        AgentConfig::new(AgentKind::Codex).with_mcp_server(McpServer::stdio("my.docs", "docs"));
+    let err = AgentSession::spawn(config, "true").err();
+    assert!(
+        matches!(err, Some(Error::UnsupportedOption { ref option, .. }) if option.contains("my.docs")),
+        "unexpected result: {err:?}"
+    );
+}
+
+#[test]
+fn test_config_round_trips_through_json() -> serde_json::Result<()> {
+    let config = AgentConfig::new(AgentKind::Gemini)
+        .with_permission_mode(PermissionMode::AcceptEdits)
+        .with_prompt_delivery(PromptDelivery::TempFile)
+        .with_timeout(Duration::from_millis(2500))
+        .with_env_remove("HTTP_PROXY")
+        .with_mcp_server(McpServer::stdio("docs", "docs-mcp").with_env("TOKEN", "x"));
+    let json = serde_json::to_value(&config)?;
+    assert_eq!(json["kind"], "gemini");
+    assert_eq!(json["permission_mode"], "accept_edits");
+    assert_eq!(json["timeout"], 2.5);
+    let back: AgentConfig = serde_json::from_value(json)?;
+    assert_eq!(back.kind, AgentKind::Gemini);
+    assert_eq!(back.prompt_delivery, PromptDelivery::TempFile);
+    assert_eq!(back.timeout, config.timeout);
+    assert_eq!(back.env, config.env);
+    assert_eq!(back.mcp_servers, config.mcp_servers);
+    Ok(())
+}
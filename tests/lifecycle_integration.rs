@@ -0,0 +1,325 @@
+//! Integration tests for turn lifecycle handling against fake CLIs.
+
+mod common;
+
+use agent_cli_runner::{AgentConfig, AgentEvent, AgentKind, AgentSession, ErrorKind, TurnSummary};
+use std::time::{Duration, Instant};
+
+fn run(config: AgentConfig, prompt: &str) -> agent_cli_runner::Result<Vec<AgentEvent>> {
+    common::install_fake_clis();
+    let mut session = AgentSession::spawn(config, prompt)?;
+    let events = session.events()?.collect();
+    Ok(events)
+}
+
+const fn is_timeout(event: &AgentEvent) -> bool {
+    matches!(
+        event,
+        AgentEvent::Error {
+            kind: ErrorKind::Timeout,
+            ..
+        }
+    )
+}
+
+const fn killed() -> AgentEvent {
+    AgentEvent::SessionCompleted {
+        exit_code: None,
+        signal: Some(9),
+        completion_record: false,
+    }
+}
+
+#[test]
+fn test_total_timeout_terminates_turn() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Claude).with_timeout(Duration::from_millis(300));
+    let started = Instant::now();
+    let events = run(config, "sleep 30")?;
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert_eq!(events.len(), 2, "unexpected events: {events:?}");
+    assert!(is_timeout(&events[0]));
+    assert_eq!(events[1], killed());
+    Ok(())
+}
+
+#[test]
+fn test_idle_timeout_after_output() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Gemini).with_idle_timeout(Duration::from_millis(300));
+    let prompt = format!(
+        "{}; sleep 30",
+        common::print_lines(&[r#"{"type":"text","text":"working"}"#])
+    );
+    let started = Instant::now();
+    let events = run(config, &prompt)?;
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert_eq!(
+        events.first(),
+        Some(&AgentEvent::Text {
+            content: "working".to_string(),
+            is_partial: false,
+        })
+    );
+    let tail: Vec<_> = events.iter().rev().take(2).collect();
+    assert_eq!(tail[0], &killed());
+    assert!(is_timeout(tail[1]));
+    Ok(())
+}
+
+#[test]
+fn test_idle_timeout_resets_on_output() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Gemini).with_idle_timeout(Duration::from_secs(2));
+    let line = r#"{"type":"text","text":"tick"}"#;
+    let prompt = format!("for i in 1 2 3 4; do printf '%s\\n' '{line}'; sleep 0.6; done");
+    let events = run(config, &prompt)?;
+    assert_eq!(events.len(), 5, "unexpected events: {events:?}");
+    assert!(!events.iter().any(is_timeout));
+    assert_eq!(
+        events.last(),
+        Some(&AgentEvent::SessionCompleted {
+            exit_code: Some(0),
+            signal: None,
+            completion_record: false,
+        })
+    );
+    Ok(())
+}
+
+fn cancel_after(session: &AgentSession, delay: Duration) -> std::thread::JoinHandle<()> {
+    let handle = session.cancel_handle();
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        handle.cancel();
+    })
+}
+
+#[test]
+fn test_cancel_ends_stream_with_cancelled() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Codex);
+    let mut session = AgentSession::spawn(config, "sleep 30")?;
+    let canceller = cancel_after(&session, Duration::from_millis(200));
+    let started = Instant::now();
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    let _ = canceller.join();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(events.last(), Some(&AgentEvent::Cancelled));
+    assert!(session.cancel_handle().is_cancelled());
+    Ok(())
+}
+
+#[test]
+fn test_cancel_lets_cli_flush_final_events() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config =
+        AgentConfig::new(AgentKind::Claude).with_cancel_grace_period(Duration::from_secs(20));
+    let farewell = common::print_lines(&[r#"{"type":"text","text":"interrupted"}"#]);
+    let prompt =
+        format!("on_int() {{ {farewell}; kill $!; exit 130; }}; trap on_int INT; sleep 30 & wait");
+    let mut session = AgentSession::spawn(config, &prompt)?;
+    let canceller = cancel_after(&session, Duration::from_millis(300));
+    let started = Instant::now();
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    let _ = canceller.join();
+    assert!(
+        started.elapsed() < Duration::from_secs(10),
+        "SIGINT was not delivered"
+    );
+    assert_eq!(
+        events,
+        vec![
+            AgentEvent::Text {
+                content: "interrupted".to_string(),
+                is_partial: false,
+            },
+            AgentEvent::SessionCompleted {
+                exit_code: Some(130),
+                signal: None,
+                completion_record: false,
+            },
+            AgentEvent::Cancelled,
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cancel_escalates_when_signals_are_ignored() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config =
+        AgentConfig::new(AgentKind::Gemini).with_cancel_grace_period(Duration::from_millis(200));
+    let mut session = AgentSession::spawn(config, "trap '' INT TERM; sleep 30")?;
+    let canceller = cancel_after(&session, Duration::from_millis(200));
+    let started = Instant::now();
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    let _ = canceller.join();
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert_eq!(events.last(), Some(&AgentEvent::Cancelled));
+    Ok(())
+}
+
+#[test]
+fn test_cancel_after_completion_is_noop() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let prompt = common::print_lines(&[r#"{"type":"text","text":"done"}"#]);
+    let mut session = AgentSession::spawn(AgentConfig::new(AgentKind::Gemini), &prompt)?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    session.cancel_handle().cancel();
+    assert!(!events.contains(&AgentEvent::Cancelled));
+    Ok(())
+}
+
+fn completions(events: &[AgentEvent]) -> Vec<&AgentEvent> {
+    events
+        .iter()
+        .filter(|e| matches!(e, AgentEvent::SessionCompleted { .. }))
+        .collect()
+}
+
+#[test]
+fn test_completion_reports_clean_exit_and_record() -> agent_cli_runner::Result<()> {
+    let result = r#"{"type":"result","usage":{"input_tokens":1,"output_tokens":1}}"#;
+    let prompt = format!("{}; exit 0", common::print_lines(&[result]));
+    let events = run(AgentConfig::new(AgentKind::Claude), &prompt)?;
+    assert_eq!(
+        completions(&events),
+        vec![&AgentEvent::SessionCompleted {
+            exit_code: Some(0),
+            signal: None,
+            completion_record: true,
+        }]
+    );
+    assert!(matches!(
+        events.last(),
+        Some(AgentEvent::SessionCompleted { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_completion_reports_crash_exit_code() -> agent_cli_runner::Result<()> {
+    let prompt = "echo 'fatal: out of cheese' >&2; exit 3";
+    let events = run(AgentConfig::new(AgentKind::Codex), prompt)?;
+    assert_eq!(
+        events,
+        vec![
+            AgentEvent::Error {
+                kind: ErrorKind::Stderr,
+                message: "fatal: out of cheese".to_string(),
+            },
+            AgentEvent::SessionCompleted {
+                exit_code: Some(3),
+                signal: None,
+                completion_record: false,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_completion_reports_terminating_signal() -> agent_cli_runner::Result<()> {
+    let events = run(AgentConfig::new(AgentKind::Gemini), "kill -9 $$")?;
+    assert_eq!(events, vec![killed()]);
+    Ok(())
+}
+
+#[test]
+fn test_gemini_result_error_status() -> agent_cli_runner::Result<()> {
+    let lines = [
+        r#"{"type":"init","session_id":"s-1","model":"gemini-2.5-flash"}"#,
+        r#"{"type":"result","status":"error","error":{"type":"quota","message":"Quota exceeded"},"stats":{"input_tokens":10,"output_tokens":0}}"#,
+    ];
+    let events = run(
+        AgentConfig::new(AgentKind::Gemini),
+        &common::print_lines(&lines),
+    )?;
+    assert!(
+        events.contains(&AgentEvent::Error {
+            kind: ErrorKind::Agent,
+            message: "Quota exceeded".to_string(),
+        }),
+        "unexpected events: {events:?}"
+    );
+    assert!(matches!(
+        events.last(),
+        Some(AgentEvent::SessionCompleted {
+            completion_record: true,
+            ..
+        })
+    ));
+    Ok(())
+}
+
+fn summaries(events: &[AgentEvent]) -> Vec<&TurnSummary> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            AgentEvent::TurnSummary(summary) => Some(summary),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_turn_summaries() -> agent_cli_runner::Result<()> {
+    let claude = [
+        r#"{"type":"result","subtype":"error_max_turns","is_error":true,"duration_ms":8123,"duration_api_ms":7012,"num_turns":3,"result":"Stopped early","stop_reason":"tool_use","total_cost_usd":0.0421,"usage":{"input_tokens":10,"output_tokens":5}}"#,
+    ];
+    let events = run(
+        AgentConfig::new(AgentKind::Claude),
+        &common::print_lines(&claude),
+    )?;
+    assert_eq!(
+        summaries(&events),
+        [&TurnSummary {
+            cost_usd: Some(0.0421),
+            duration: Some(Duration::from_millis(8123)),
+            api_duration: Some(Duration::from_millis(7012)),
+            num_turns: Some(3),
+            result: Some("Stopped early".to_string()),
+            is_error: true,
+            subtype: Some("error_max_turns".to_string()),
+            stop_reason: Some("tool_use".to_string()),
+        }]
+    );
+    let gemini = [
+        r#"{"type":"result","status":"error","error":{"type":"quota","message":"Quota exceeded"},"stats":{"duration_ms":40}}"#,
+    ];
+    let events = run(
+        AgentConfig::new(AgentKind::Gemini),
+        &common::print_lines(&gemini),
+    )?;
+    assert_eq!(
+        summaries(&events),
+        [&TurnSummary {
+            duration: Some(Duration::from_millis(40)),
+            is_error: true,
+            subtype: Some("quota".to_string()),
+            ..TurnSummary::default()
+        }]
+    );
+    let codex = [r#"{"type":"turn.failed","error":{"message":"stream disconnected"}}"#];
+    let events = run(
+        AgentConfig::new(AgentKind::Codex),
+        &common::print_lines(&codex),
+    )?;
+    assert_eq!(
+        summaries(&events),
+        [&TurnSummary {
+            is_error: true,
+            ..TurnSummary::default()
+        }]
+    );
+    for (kind, line) in [
+        (AgentKind::Claude, r#"{"type":"result"}"#),
+        (AgentKind::Gemini, r#"{"type":"result","status":"success"}"#),
+        (AgentKind::Codex, r#"{"type":"turn.completed"}"#),
+    ] {
+        let events = run(AgentConfig::new(kind), &common::print_lines(&[line]))?;
+        assert!(
+            summaries(&events).is_empty(),
+            "unexpected events: {events:?}"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,510 @@
+//! Integration tests that replay recorded CLI output through the parsers.
+
+mod common;
+
+use agent_cli_runner::{
+    AgentConfig, AgentEvent, AgentKind, AgentSession, ErrorKind, McpServer, McpServerStatus,
+    SessionMetadata, ToolCall, ToolResult, TurnSummary, Usage,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn replay(kind: AgentKind, prompt: &str) -> agent_cli_runner::Result<Vec<AgentEvent>> {
+    common::install_fake_clis();
+    let mut session = AgentSession::spawn(AgentConfig::new(kind), prompt)?;
+    let events = session.events()?.collect();
+    Ok(events)
+}
+
+fn tool_results(events: &[AgentEvent]) -> Vec<&ToolResult> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            AgentEvent::ToolResult(result) => Some(result),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_claude_fixture_tool_results() -> agent_cli_runner::Result<()> {
+    for (fixture, id, output) in [
+        (
+            "schema_extraction/claude-stream-20251222-024235.log",
+            "toolu_legacy_001",
+            "Tests passed!",
+        ),
+        (
+            "schema_extraction/agent-stream-20251223-022408.log",
+            "toolu_001",
+            "File contents here",
+        ),
+    ] {
+        let events = replay(
+            AgentKind::Claude,
+            &common::replay_fixture(fixture, "claude"),
+        )?;
+        assert_eq!(
+            tool_results(&events),
+            vec![&ToolResult {
+                tool_call_id: id.to_string(),
+                output: output.to_string(),
+                success: true,
+            }],
+            "fixture {fixture}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_claude_user_tool_result_content_blocks() -> agent_cli_runner::Result<()> {
+    let lines = [
+        r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_1","content":[{"type":"text","text":"line one"},{"type":"text","text":"line two"}]}]}}"#,
+        r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_2","content":[{"type":"text","text":"screenshot"},{"type":"image","source":{"type":"base64","media_type":"image/png","data":"iVBORw0KGgo="}}]}]}}"#,
+        r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_3","content":"command not found","is_error":true}]}}"#,
+    ];
+    let events = replay(AgentKind::Claude, &common::print_lines(&lines))?;
+    let expected = [
+        ("toolu_1", "line one\nline two", true),
+        ("toolu_2", "screenshot\n[image: image/png]", true),
+        ("toolu_3", "command not found", false),
+    ]
+    .map(|(id, output, success)| ToolResult {
+        tool_call_id: id.to_string(),
+        output: output.to_string(),
+        success,
+    });
+    assert_eq!(tool_results(&events), expected.iter().collect::<Vec<_>>());
+    Ok(())
+}
+
+#[test]
+fn test_claude_user_text_is_not_agent_output() -> agent_cli_runner::Result<()> {
+    let lines = [
+        r#"{"type":"user","message":{"role":"user","content":"Fix the tests"}}"#,
+        r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"Also lint"}]}}"#,
+    ];
+    let events = replay(AgentKind::Claude, &common::print_lines(&lines))?;
+    assert!(
+        !events.iter().any(|e| matches!(e, AgentEvent::Text { .. })),
+        "unexpected events: {events:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_codex_exec_fixture() -> agent_cli_runner::Result<()> {
+    let events = replay(
+        AgentKind::Codex,
+        &common::replay_fixture("codex/exec-stream.log", "codex"),
+    )?;
+    assert_eq!(
+        events.first(),
+        Some(&AgentEvent::SessionStarted {
+            session_id: Some("0199a213-81c0-7800-8aa1-bbab2a035a53".to_string()),
+            metadata: SessionMetadata::default(),
+        })
+    );
+    assert!(events.contains(&AgentEvent::Thinking {
+        content: Some("**Inspecting the repository**".to_string()),
+        is_partial: false,
+        is_redacted: false,
+        signature: None,
+    }));
+    let calls: Vec<(&str, &str, Option<&str>)> = events
+        .iter()
+        .filter_map(|e| match e {
+            AgentEvent::ToolCall(call) => {
+                Some((call.id.as_str(), call.name.as_str(), call.server.as_deref()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        calls,
+        vec![
+            ("item_1", "command_execution", None),
+            ("item_2", "command_execution", None),
+            ("item_3", "file_change", None),
+            ("item_4", "search", Some("docs")),
+            ("item_5", "todo_list", None),
+        ]
+    );
+    let results: Vec<(&str, &str, bool)> = tool_results(&events)
+        .into_iter()
+        .map(|r| (r.tool_call_id.as_str(), r.output.as_str(), r.success))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            ("item_1", "Cargo.toml\nsrc\n", true),
+            ("item_2", "test result: FAILED\n", false),
+            ("item_3", "update src/lib.rs", true),
+            ("item_4", "assert_eq! compares two values", true),
+            ("item_5", "[x] List files\n[x] Fix the test", true),
+        ]
+    );
+    assert!(events.contains(&AgentEvent::Text {
+        content: "Fixed the failing assertion in src/lib.rs.".to_string(),
+        is_partial: false,
+    }));
+    assert!(events.contains(&AgentEvent::Usage(Usage {
+        input_tokens: 24763,
+        output_tokens: 122,
+        cache_read_tokens: Some(24448),
+        cache_write_tokens: None,
+    })));
+    assert_eq!(
+        events.last(),
+        Some(&AgentEvent::SessionCompleted {
+            exit_code: Some(0),
+            signal: None,
+            completion_record: true,
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mcp_tool_calls_are_tagged_with_server() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let docs = McpServer::url("docs", "https://mcp.example.com/docs");
+    for (kind, line, name, server) in [
+        (
+            AgentKind::Claude,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"mcp__docs__search","input":{}}]}}"#,
+            "mcp__docs__search",
+            Some("docs"),
+        ),
+        (
+            AgentKind::Gemini,
+            r#"{"type":"tool_use","tool_name":"docs__search","tool_id":"t1","parameters":{}}"#,
+            "docs__search",
+            Some("docs"),
+        ),
+        (
+            AgentKind::Gemini,
+            r#"{"type":"tool_use","tool_name":"read__file","tool_id":"t1","parameters":{}}"#,
+            "read__file",
+            None,
+        ),
+    ] {
+        let config = AgentConfig::new(kind).with_mcp_server(docs.clone());
+        let mut session = AgentSession::spawn(config, &common::print_lines(&[line]))?;
+        let events: Vec<AgentEvent> = session.events()?.collect();
+        assert_eq!(
+            events.first(),
+            Some(&AgentEvent::ToolCall(ToolCall {
+                id: "t1".to_string(),
+                name: name.to_string(),
+                input: serde_json::json!({}),
+                server: server.map(String::from),
+            }))
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_codex_exec_turn_failed() -> agent_cli_runner::Result<()> {
+    let lines = [
+        r#"{"type":"thread.started","thread_id":"t-1"}"#,
+        r#"{"type":"turn.started"}"#,
+        r#"{"type":"error","message":"stream disconnected"}"#,
+        r#"{"type":"turn.failed","error":{"message":"stream disconnected"}}"#,
+    ];
+    let events = replay(AgentKind::Codex, &common::print_lines(&lines))?;
+    let agent_errors = events
+        .iter()
+        .filter(|e| {
+            matches!(e, AgentEvent::Error { kind: ErrorKind::Agent, message } if message == "stream disconnected")
+        })
+        .count();
+    assert_eq!(agent_errors, 2, "unexpected events: {events:?}");
+    assert!(matches!(
+        events.last(),
+        Some(AgentEvent::SessionCompleted {
+            completion_record: true,
+            ..
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_codex_legacy_events_still_parse() -> agent_cli_runner::Result<()> {
+    let lines = [
+        r#"{"event":"session_start","session_id":"legacy-1"}"#,
+        r#"{"type":"thread.started","thread_id":"t-2"}"#,
+    ];
+    let events = replay(AgentKind::Codex, &common::print_lines(&lines))?;
+    let sessions: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            AgentEvent::SessionStarted { session_id, .. } => Some(session_id.as_deref()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sessions, vec![Some("legacy-1"), Some("t-2")]);
+    Ok(())
+}
+
+#[test]
+fn test_gemini_stream_json_fixture() -> agent_cli_runner::Result<()> {
+    let events = replay(
+        AgentKind::Gemini,
+        &common::replay_fixture("gemini/stream-json.log", "gemini"),
+    )?;
+    let text = |content: &str| AgentEvent::Text {
+        content: content.to_string(),
+        is_partial: true,
+    };
+    assert_eq!(
+        events,
+        vec![
+            AgentEvent::SessionStarted {
+                session_id: Some("c25acda3-b110-46c2-9d6c-2ef0d4b6b0d2".to_string()),
+                metadata: SessionMetadata {
+                    model: Some("gemini-2.5-pro".to_string()),
+                    ..SessionMetadata::default()
+                },
+            },
+            AgentEvent::ToolCall(ToolCall {
+                id: "read_file-1763632934020-2f1b".to_string(),
+                name: "read_file".to_string(),
+                input: serde_json::json!({"absolute_path": "/workspace/README.md"}),
+                server: None,
+            }),
+            AgentEvent::ToolResult(ToolResult {
+                tool_call_id: "read_file-1763632934020-2f1b".to_string(),
+                output: "# Demo project".to_string(),
+                success: true,
+            }),
+            AgentEvent::ToolCall(ToolCall {
+                id: "read_file-1763632934540-9c3d".to_string(),
+                name: "read_file".to_string(),
+                input: serde_json::json!({"absolute_path": "/workspace/CHANGELOG.md"}),
+                server: None,
+            }),
+            AgentEvent::ToolResult(ToolResult {
+                tool_call_id: "read_file-1763632934540-9c3d".to_string(),
+                output: "File not found: /workspace/CHANGELOG.md".to_string(),
+                success: false,
+            }),
+            AgentEvent::Error {
+                kind: ErrorKind::Agent,
+                message: "Loop detection triggered".to_string(),
+            },
+            text("The README describes "),
+            text("a demo project."),
+            AgentEvent::Usage(Usage {
+                input_tokens: 8790,
+                output_tokens: 131,
+                cache_read_tokens: Some(4096),
+                cache_write_tokens: None,
+            }),
+            AgentEvent::TurnSummary(TurnSummary {
+                duration: Some(Duration::from_millis(5012)),
+                ..TurnSummary::default()
+            }),
+            AgentEvent::SessionCompleted {
+                exit_code: Some(0),
+                signal: None,
+                completion_record: true,
+            },
+        ]
+    );
+    Ok(())
+}
+
+fn thinking(events: &[AgentEvent]) -> Vec<(Option<&str>, bool, bool, Option<&str>)> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            AgentEvent::Thinking {
+                content,
+                is_partial,
+                is_redacted,
+                signature,
+            } => Some((
+                content.as_deref(),
+                *is_partial,
+                *is_redacted,
+                signature.as_deref(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_thinking_content_from_each_cli() -> agent_cli_runner::Result<()> {
+    let claude = [
+        r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"Check the lockfile","signature":"c2ln"},{"type":"redacted_thinking","data":"ZW5j"},{"type":"text","text":"Done"}]}}"#,
+    ];
+    let events = replay(AgentKind::Claude, &common::print_lines(&claude))?;
+    assert_eq!(
+        thinking(&events),
+        [
+            (Some("Check the lockfile"), false, false, Some("c2ln")),
+            (None, false, true, None)
+        ]
+    );
+    let codex = [
+        r#"{"type":"item.completed","item":{"id":"item_0","type":"reasoning","text":"Reading the tests"}}"#,
+        r#"{"event":"thinking","text":"Planning"}"#,
+    ];
+    let events = replay(AgentKind::Codex, &common::print_lines(&codex))?;
+    assert_eq!(
+        thinking(&events),
+        [
+            (Some("Reading the tests"), false, false, None),
+            (Some("Planning"), false, false, None)
+        ]
+    );
+    let gemini = [
+        r#"{"type":"message","role":"assistant","content":"Weighing ","delta":true,"thought":true}"#,
+        r#"{"type":"thought","value":{"subject":"Scope","description":"Only the parser changes"}}"#,
+        r#"{"type":"message","role":"assistant","content":"Answer","delta":true}"#,
+    ];
+    let events = replay(AgentKind::Gemini, &common::print_lines(&gemini))?;
+    assert_eq!(
+        thinking(&events),
+        [
+            (Some("Weighing "), true, false, None),
+            (Some("Scope: Only the parser changes"), false, false, None)
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_claude_partial_messages() -> agent_cli_runner::Result<()> {
+    let lines = [
+        r#"{"type":"stream_event","event":{"type":"message_start","message":{"id":"msg_1"}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Read the diff"}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"c2ln"}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_stop","index":0}}"#,
+        r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"Read the diff","signature":"c2ln"}]}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Hel"}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"lo"}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_stop","index":1}}"#,
+        r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Hello"}]}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"toolu_1","name":"mcp__docs__search","input":{}}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"{\"query\":"}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":" \"rust\"}"}}}"#,
+        r#"{"type":"stream_event","event":{"type":"content_block_stop","index":2}}"#,
+        r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"toolu_1","name":"mcp__docs__search","input":{"query":"rust"}}]}}"#,
+    ];
+    common::install_fake_clis();
+    let prompt = format!(r#"printf 'arg:%s\n' "$@"; {}"#, common::print_lines(&lines));
+    let config = AgentConfig::new(AgentKind::Claude).with_partial_messages();
+    let mut session = AgentSession::spawn(config, &prompt)?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    let args: Vec<&str> = events
+        .iter()
+        .filter_map(|e| match e {
+            AgentEvent::Error {
+                kind: ErrorKind::UnparsedOutput,
+                message,
+            } => message.strip_prefix("arg:"),
+            _ => None,
+        })
+        .collect();
+    assert!(
+        args.windows(2)
+            .any(|w| w == ["--include-partial-messages", "--verbose"]),
+        "{args:?}"
+    );
+    let text = |content: &str, is_partial| AgentEvent::Text {
+        content: content.to_string(),
+        is_partial,
+    };
+    let streamed: Vec<&AgentEvent> = events
+        .iter()
+        .filter(|e| {
+            !matches!(
+                e,
+                AgentEvent::Error { .. } | AgentEvent::SessionCompleted { .. }
+            )
+        })
+        .collect();
+    assert_eq!(
+        streamed,
+        [
+            &AgentEvent::Thinking {
+                content: Some("Read the diff".to_string()),
+                is_partial: true,
+                is_redacted: false,
+                signature: None,
+            },
+            &AgentEvent::Thinking {
+                content: None,
+                is_partial: false,
+                is_redacted: false,
+                signature: Some("c2ln".to_string()),
+            },
+            &text("Hel", true),
+            &text("lo", true),
+            &AgentEvent::ToolCall(ToolCall {
+                id: "toolu_1".to_string(),
+                name: "mcp__docs__search".to_string(),
+                input: serde_json::json!({ "query": "rust" }),
+                server: Some("docs".to_string()),
+            }),
+        ]
+    );
+    Ok(())
+}
+
+fn metadata(events: &[AgentEvent]) -> Vec<&SessionMetadata> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            AgentEvent::SessionStarted { metadata, .. } => Some(metadata),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_claude_init_metadata() -> agent_cli_runner::Result<()> {
+    let events = replay(
+        AgentKind::Claude,
+        &common::replay_fixture(
+            "schema_extraction/agent-stream-20251223-022408.log",
+            "claude",
+        ),
+    )?;
+    assert_eq!(
+        metadata(&events),
+        [&SessionMetadata {
+            model: Some("claude-opus-4-5-20251101".to_string()),
+            tools: vec!["Bash".to_string(), "Read".to_string(), "Write".to_string()],
+            mcp_servers: Vec::new(),
+            permission_mode: Some("bypassPermissions".to_string()),
+            cwd: Some(PathBuf::from("/workspaces/test")),
+            cli_version: Some("2.0.76".to_string()),
+        }]
+    );
+    let lines = [
+        r#"{"type":"system","subtype":"init","session_id":"s-1","mcp_servers":[{"name":"docs","status":"connected"},{"name":"tickets","status":"failed"}]}"#,
+        r#"{"type":"system","subtype":"compact_boundary","session_id":"s-1"}"#,
+    ];
+    let events = replay(AgentKind::Claude, &common::print_lines(&lines))?;
+    let servers = match metadata(&events)[..] {
+        [metadata] => metadata.mcp_servers.clone(),
+        _ => Vec::new(),
+    };
+    assert_eq!(
+        servers,
+        [("docs", "connected"), ("tickets", "failed")].map(|(name, status)| McpServerStatus {
+            name: name.to_string(),
+            status: Some(status.to_string()),
+        })
+    );
+    Ok(())
+}
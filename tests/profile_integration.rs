@@ -0,0 +1,103 @@
+//! Integration tests for named configuration profiles.
+
+use agent_cli_runner::{AgentConfig, AgentKind, Error, McpServer, PermissionMode};
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn profile_fixture(name: &str) -> PathBuf {
+    std::env::set_var("AGENT_PROFILE_TOKEN", "tok-123");
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/profiles")
+        .join(name)
+}
+
+#[test]
+fn test_profile_inherits_and_interpolates() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::from_profile_file(profile_fixture("agents.json"), "review")?;
+    assert_eq!(config.kind, AgentKind::Claude);
+    assert_eq!(config.model.as_deref(), Some("sonnet"));
+    assert_eq!(config.permission_mode, Some(PermissionMode::ReadOnly));
+    assert_eq!(config.timeout, Some(Duration::from_secs(90)));
+    assert_eq!(config.idle_timeout, Some(Duration::from_millis(1500)));
+    assert_eq!(config.cancel_grace_period, Duration::from_secs(5));
+    assert_eq!(config.channel_buffer_size, 100);
+    assert_eq!(config.working_dir, Some(PathBuf::from("/srv/${repo}")));
+    assert_eq!(config.extra_args, ["--verbose"]);
+    assert_eq!(
+        config.env.get("PROFILE_REGION"),
+        Some(&Some("us".to_string()))
+    );
+    assert_eq!(
+        config.env.get("PROFILE_TOKEN"),
+        Some(&Some("tok-123".to_string()))
+    );
+    assert_eq!(
+        config.mcp_servers,
+        [
+            McpServer::stdio("docs", "docs-mcp").with_args(["--index", "api"]),
+            McpServer::url("tickets", "https://mcp.example.com/tickets"),
+        ]
+    );
+    Ok(())
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn test_toml_profile() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::from_profile_file(profile_fixture("agents.toml"), "ci")?;
+    assert_eq!(config.kind, AgentKind::Codex);
+    assert_eq!(config.model.as_deref(), Some("gpt-5-codex"));
+    assert_eq!(config.permission_mode, Some(PermissionMode::WorkspaceWrite));
+    assert_eq!(
+        config.prompt_delivery,
+        agent_cli_runner::PromptDelivery::Stdin
+    );
+    assert_eq!(config.max_turns, Some(8));
+    assert_eq!(config.cancel_grace_period, Duration::from_secs(2));
+    assert_eq!(
+        config.env.get("PROFILE_TOKEN"),
+        Some(&Some("tok-123".to_string()))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_profile_errors_name_the_offending_key() {
+    std::env::remove_var("AGENT_PROFILE_UNSET");
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("invalid-profiles.json");
+    let _ = std::fs::write(
+        &path,
+        r#"{
+            "base": {"kind": "claude", "max_turns": "many"},
+            "typo": {"kind": "codex", "modle": "o3"},
+            "unset": {"kind": "gemini", "env": {"KEY": "${AGENT_PROFILE_UNSET}"}},
+            "inherited": {"inherits": "unset", "model": "flash"},
+            "overridden": {"inherits": "unset", "env": {"KEY": "set"}},
+            "orphan": {"inherits": "missing"},
+            "loop": {"inherits": "loop"},
+            "custom": {"kind": "aider"}
+        }"#,
+    );
+    for (profile, key) in [
+        ("base", "base.max_turns"),
+        ("typo", "typo.modle"),
+        ("unset", "unset.env.KEY"),
+        ("inherited", "unset.env.KEY"),
+        ("orphan", "orphan.inherits"),
+        ("loop", "loop.inherits"),
+        ("custom", "custom.kind"),
+        ("absent", "absent"),
+    ] {
+        let err = AgentConfig::from_profile_file(&path, profile).err();
+        assert!(
+            matches!(err, Some(Error::InvalidProfile { key: Some(ref got), .. }) if got == key),
+            "unexpected result for {profile}: {err:?}"
+        );
+    }
+    let overridden = AgentConfig::from_profile_file(&path, "overridden");
+    assert!(
+        matches!(&overridden, Ok(config) if config.env.get("KEY") == Some(&Some("set".to_string()))),
+        "unexpected result for overridden: {:?}",
+        overridden.err()
+    );
+}
@@ -1,6 +1,16 @@
 //! Integration tests for session management across CLIs.
 
-use agent_cli_runner::{AgentConfig, AgentKind};
+mod common;
+
+use agent_cli_runner::{
+    AgentBackend, AgentConfig, AgentEvent, AgentKind, AgentParser, AgentSession, AuthCheck,
+    CliFeature, Error, ErrorKind, EventEnvelope, EventSource, PermissionMode, PromptDelivery,
+    TurnSummary,
+};
+use serde_json::Value;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[test]
 fn test_config_builder() {
@@ -21,9 +31,12 @@ fn test_agent_kind_properties() {
     assert_eq!(AgentKind::Claude.binary_name(), "claude");
     assert_eq!(AgentKind::Codex.binary_name(), "codex");
     assert_eq!(AgentKind::Gemini.binary_name(), "gemini");
-    assert_eq!(AgentKind::Claude.api_key_env_var(), "ANTHROPIC_API_KEY");
-    assert_eq!(AgentKind::Codex.api_key_env_var(), "OPENAI_API_KEY");
-    assert_eq!(AgentKind::Gemini.api_key_env_var(), "GOOGLE_API_KEY");
+    assert_eq!(
+        AgentKind::Claude.api_key_env_var(),
+        Some("ANTHROPIC_API_KEY")
+    );
+    assert_eq!(AgentKind::Codex.api_key_env_var(), Some("OPENAI_API_KEY"));
+    assert_eq!(AgentKind::Gemini.api_key_env_var(), Some("GOOGLE_API_KEY"));
     assert_eq!(AgentKind::Claude.display_name(), "Claude Code");
     assert_eq!(AgentKind::Codex.display_name(), "Codex CLI");
     assert_eq!(AgentKind::Gemini.display_name(), "Gemini CLI");
@@ -43,3 +56,483 @@ fn test_config_with_session_id() {
         .with_session_id("test-session-123");
     assert_eq!(config.session_id, Some("test-session-123".to_string()));
 }
+
+fn texts(session: &mut AgentSession) -> agent_cli_runner::Result<Vec<String>> {
+    Ok(session
+        .events()?
+        .filter_map(|e| match e {
+            AgentEvent::Text { content, .. } => Some(content),
+            _ => None,
+        })
+        .collect())
+}
+
+#[test]
+fn test_codex_resumes_captured_thread() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let started = common::print_lines(&[r#"{"type":"thread.started","thread_id":"t-42"}"#]);
+    let mut session = AgentSession::spawn(AgentConfig::new(AgentKind::Codex), &started)?;
+    assert!(texts(&mut session)?.is_empty());
+    assert_eq!(session.session_id(), Some("t-42"));
+
+    let reply = common::print_lines(&[
+        r#"{"type":"item.completed","item":{"id":"i","type":"agent_message","text":"resumed"}}"#,
+    ]);
+    session.send_input(&format!(r#"[ "$3 $4" = "resume t-42" ] && {reply}"#))?;
+    assert_eq!(texts(&mut session)?, vec!["resumed".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn test_codex_legacy_session_cannot_resume() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let started = common::print_lines(&[r#"{"event":"session_start","session_id":"old"}"#]);
+    let mut session = AgentSession::spawn(AgentConfig::new(AgentKind::Codex), &started)?;
+    let _ = texts(&mut session)?;
+    assert_eq!(session.session_id(), Some("old"));
+    assert!(matches!(
+        session.send_input("true"),
+        Err(Error::MultiTurnNotSupported { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_send_input_before_session_start() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let mut session = AgentSession::spawn(AgentConfig::new(AgentKind::Codex), "true")?;
+    assert!(matches!(
+        session.send_input("true"),
+        Err(Error::NoSessionId)
+    ));
+    Ok(())
+}
+
+/// Starts a persistent Claude session driven by `script`.
+///
+/// A persistent Claude command has no prompt argument, so the script is
+/// passed as the resumed session ID, which the fake CLI evaluates as its
+/// last argument.
+fn persistent(config: AgentConfig, script: &str) -> agent_cli_runner::Result<AgentSession> {
+    common::install_fake_clis();
+    AgentSession::spawn(
+        config.with_persistent_session().with_session_id(script),
+        "first",
+    )
+}
+
+const ECHO_TURNS: &str = r#"n=0; while read -r line; do case "$line" in *'"type":"user"'*) n=$((n+1)); printf '{"type":"assistant","message":{"content":[{"type":"text","text":"turn %s"}]}}\n' "$n"; printf '%s\n' '{"type":"result","subtype":"success"}';; esac; done"#;
+
+#[test]
+fn test_persistent_session_keeps_process_across_turns() -> agent_cli_runner::Result<()> {
+    let mut session = persistent(AgentConfig::new(AgentKind::Claude), ECHO_TURNS)?;
+    let first: Vec<AgentEvent> = session.events()?.collect();
+    assert_eq!(
+        first,
+        vec![
+            AgentEvent::Text {
+                content: "turn 1".to_string(),
+                is_partial: false,
+            },
+            AgentEvent::TurnSummary(TurnSummary {
+                subtype: Some("success".to_string()),
+                ..TurnSummary::default()
+            }),
+            AgentEvent::TurnCompleted,
+        ]
+    );
+    session.send_input("second")?;
+    assert_eq!(texts(&mut session)?, vec!["turn 2".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn test_persistent_session_idle_timeout_pauses_between_turns() -> agent_cli_runner::Result<()> {
+    let config = AgentConfig::new(AgentKind::Claude).with_idle_timeout(Duration::from_millis(300));
+    let mut session = persistent(config, ECHO_TURNS)?;
+    assert_eq!(texts(&mut session)?, vec!["turn 1".to_string()]);
+    std::thread::sleep(Duration::from_millis(800));
+    session.send_input("second")?;
+    assert_eq!(texts(&mut session)?, vec!["turn 2".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn test_persistent_session_reports_closed_stdin() -> agent_cli_runner::Result<()> {
+    let mut session = persistent(AgentConfig::new(AgentKind::Claude), "exec 0<&-; sleep 0.2")?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    assert!(matches!(
+        events.last(),
+        Some(AgentEvent::SessionCompleted { .. })
+    ));
+    assert!(matches!(
+        session.send_input("again"),
+        Err(Error::StdinWriteFailed { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_persistent_session_requires_claude() {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Gemini).with_persistent_session();
+    assert!(matches!(
+        AgentSession::spawn(config, "true"),
+        Err(Error::MultiTurnNotSupported { .. })
+    ));
+}
+
+/// A backend for a made-up CLI that runs the prompt with `sh` and prints
+/// `{"say": ...}` lines.
+#[derive(Debug)]
+struct ShellBackend;
+
+impl AgentParser for ShellBackend {
+    fn parse(&self, json: &Value) -> Vec<AgentEvent> {
+        json.get("say")
+            .and_then(Value::as_str)
+            .map(|text| AgentEvent::Text {
+                content: text.to_string(),
+                is_partial: false,
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+impl AgentBackend for ShellBackend {
+    fn binary_name(&self) -> &'static str {
+        "sh"
+    }
+
+    fn build_command(&self, config: &AgentConfig, prompt: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        if let Some(ref session_id) = config.session_id {
+            cmd.env("RESUMED", session_id);
+        }
+        cmd.arg("-c").arg(prompt);
+        cmd
+    }
+}
+
+#[test]
+fn test_custom_backend_session() -> agent_cli_runner::Result<()> {
+    let backend: Arc<dyn AgentBackend> = Arc::new(ShellBackend);
+    let kind = AgentKind::Custom(backend);
+    assert_eq!(kind.binary_name(), "sh");
+    assert_eq!(kind.display_name(), "sh");
+    assert_eq!(kind.api_key_env_var(), None);
+    assert_eq!(kind, kind.clone());
+    assert_ne!(kind, AgentKind::Custom(Arc::new(ShellBackend)));
+
+    let config = AgentConfig::new(kind).with_working_dir(std::env::temp_dir());
+    let mut session = AgentSession::spawn(config, r#"echo '{"say":"hi"}'; echo 'plain'"#)?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    assert_eq!(
+        events[0],
+        AgentEvent::Text {
+            content: "hi".to_string(),
+            is_partial: false,
+        }
+    );
+    assert!(matches!(
+        events[1],
+        AgentEvent::Error {
+            kind: agent_cli_runner::ErrorKind::UnparsedOutput,
+            ..
+        }
+    ));
+    Ok(())
+}
+
+fn reply_line(kind: &AgentKind, text: &str) -> String {
+    match kind {
+        AgentKind::Claude => format!(
+            r#"{{"type":"assistant","message":{{"content":[{{"type":"text","text":"{text}"}}]}}}}"#
+        ),
+        AgentKind::Codex => format!(
+            r#"{{"type":"item.completed","item":{{"id":"i","type":"agent_message","text":"{text}"}}}}"#
+        ),
+        _ => format!(r#"{{"type":"message","role":"assistant","content":"{text}"}}"#),
+    }
+}
+
+#[test]
+fn test_prompt_delivery_keeps_prompt_out_of_argv() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    for kind in [AgentKind::Claude, AgentKind::Codex, AgentKind::Gemini] {
+        for delivery in [PromptDelivery::Stdin, PromptDelivery::TempFile] {
+            let reply = common::print_lines(&[&reply_line(&kind, "delivered")]);
+            let prompt = format!(r#"case "$*" in *delivered*) exit 1 ;; esac; {reply}"#);
+            let config = AgentConfig::new(kind.clone()).with_prompt_delivery(delivery);
+            let mut session = AgentSession::spawn(config, &prompt)?;
+            assert_eq!(
+                texts(&mut session)?,
+                vec!["delivered".to_string()],
+                "{kind:?} with {delivery:?}"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_prompt_delivery_handles_prompts_beyond_arg_max() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let padding = "x".repeat(4 * 1024 * 1024);
+    let reply = common::print_lines(&[&reply_line(&AgentKind::Claude, "large")]);
+    let prompt = format!(": '{padding}'; {reply}");
+    for delivery in [PromptDelivery::Stdin, PromptDelivery::TempFile] {
+        let config = AgentConfig::new(AgentKind::Claude).with_prompt_delivery(delivery);
+        let mut session = AgentSession::spawn(config, &prompt)?;
+        assert_eq!(texts(&mut session)?, vec!["large".to_string()]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_prompt_delivery_reports_stdin_write_failure() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Claude)
+        .with_prompt_delivery(PromptDelivery::Stdin)
+        .with_model("exec 0<&-; exit 0");
+    let prompt = "x".repeat(4 * 1024 * 1024);
+    let mut session = AgentSession::spawn(config, &prompt)?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    let input_error = events.iter().position(|e| {
+        matches!(
+            e,
+            AgentEvent::Error {
+                kind: ErrorKind::Input,
+                ..
+            }
+        )
+    });
+    let completed = events
+        .iter()
+        .position(|e| matches!(e, AgentEvent::SessionCompleted { .. }));
+    assert!(
+        input_error.is_some() && input_error < completed,
+        "{events:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_prompt_delivery_does_not_block_spawn() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let config = AgentConfig::new(AgentKind::Claude)
+        .with_prompt_delivery(PromptDelivery::Stdin)
+        .with_model("sleep 5")
+        .with_timeout(Duration::from_millis(300));
+    let prompt = "x".repeat(4 * 1024 * 1024);
+    let started = Instant::now();
+    let mut session = AgentSession::spawn(config, &prompt)?;
+    assert!(started.elapsed() < Duration::from_secs(2));
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    assert!(events.iter().any(|e| matches!(
+        e,
+        AgentEvent::Error {
+            kind: ErrorKind::Timeout,
+            ..
+        }
+    )));
+    Ok(())
+}
+
+/// Installs a fake Codex that only answers `--version` and `exec --help`.
+fn versioned_codex(name: &str, version: &str, help: &str) -> std::path::PathBuf {
+    let path = common::fake_bin_dir().join("versions").join(name);
+    common::install_fake_cli_at(&path);
+    let script = format!(
+        "#!/bin/sh\ncase \"$1 $2\" in\n'--version ') echo 'codex-cli {version}' ;;\n'exec --help') printf '%s\\n' '{help}' ;;\nesac\n"
+    );
+    let _ = std::fs::write(&path, script);
+    path
+}
+
+#[test]
+fn test_probe_reports_path_and_unknown_version() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let info = AgentKind::Gemini.probe()?;
+    assert_eq!(info.path, common::fake_bin_dir().join("gemini"));
+    assert_eq!(info.version, None);
+    assert!(!info.supports(CliFeature::StreamJson));
+    Ok(())
+}
+
+#[test]
+fn test_preflight_rejects_missing_feature() {
+    common::install_fake_clis();
+    let path = versioned_codex(
+        "codex-0.20",
+        "0.20.0",
+        "Options:\n  --json  Print events as JSONL, e.g. to resume later",
+    );
+    let config = AgentConfig::new(AgentKind::Codex)
+        .with_binary_path(path)
+        .with_session_id("thread-1")
+        .with_preflight();
+    let err = AgentSession::spawn(config, "true").err();
+    assert_eq!(
+        err.map(|e| e.to_string()).as_deref(),
+        Some("codex 0.20.0 does not support resume")
+    );
+}
+
+#[test]
+fn test_preflight_accepts_supported_config() {
+    common::install_fake_clis();
+    let path = versioned_codex(
+        "codex-0.46",
+        "0.46.0",
+        "Commands:\n  resume  Resume a previous session\nOptions:\n  --json\n  --sandbox",
+    );
+    let config = AgentConfig::new(AgentKind::Codex)
+        .with_binary_path(path)
+        .with_session_id("thread-1")
+        .with_preflight();
+    assert!(AgentSession::spawn(config, "true").is_ok());
+}
+
+#[test]
+fn test_preflight_probes_once_with_configured_env() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let path = common::fake_bin_dir().join("versions").join("codex-logged");
+    let log = path.with_extension("log");
+    common::install_fake_cli_at(&path);
+    let script = format!(
+        "#!/bin/sh\necho \"$1\" >> '{}'\ncase \"$1 $2\" in\n'--version ') [ -n \"$PROBE_ENV\" ] && echo 'codex-cli 0.46.0' ;;\n'exec --help') printf '%s\\n' '  resume' '--json' ;;\n*) echo '{{\"type\":\"thread.started\",\"thread_id\":\"t1\"}}' ;;\nesac\n",
+        log.display()
+    );
+    let _ = std::fs::write(&path, script);
+    let config = AgentConfig::new(AgentKind::Codex)
+        .with_binary_path(path)
+        .with_env("PROBE_ENV", "1")
+        .with_preflight();
+    let err = AgentSession::spawn(
+        config
+            .clone()
+            .with_permission_mode(PermissionMode::ReadOnly),
+        "true",
+    )
+    .err();
+    assert_eq!(
+        err.map(|e| e.to_string()).as_deref(),
+        Some("codex 0.46.0 does not support permission modes")
+    );
+    let _ = std::fs::remove_file(&log);
+    let mut session = AgentSession::spawn(config, "true")?;
+    let _ = session.events()?.count();
+    session.send_input("again")?;
+    let _ = session.events()?.count();
+    let probes = std::fs::read_to_string(&log).unwrap_or_default();
+    assert_eq!(
+        probes.lines().filter(|line| *line == "--version").count(),
+        1
+    );
+    Ok(())
+}
+
+/// Returns a Codex config that only sees credentials under `home`.
+fn codex_at_home(home: &std::path::Path) -> AgentConfig {
+    AgentConfig::new(AgentKind::Codex)
+        .with_clean_env(["PATH"])
+        .with_env("HOME", home.display().to_string())
+}
+
+#[test]
+fn test_cached_login_counts_as_credentials() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let home = common::fake_bin_dir().join("home-logged-in");
+    let _ = std::fs::create_dir_all(home.join(".codex"));
+    let _ = std::fs::write(home.join(".codex/auth.json"), "{}");
+    let mut session = AgentSession::spawn(codex_at_home(&home), "true")?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    assert_eq!(events.len(), 1, "unexpected events: {events:?}");
+    Ok(())
+}
+
+#[test]
+fn test_auth_check_strictness() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let home = common::fake_bin_dir().join("home-logged-out");
+    assert!(matches!(
+        AgentSession::spawn(codex_at_home(&home), "true"),
+        Err(Error::ApiKeyMissing { ref env_var }) if env_var == "OPENAI_API_KEY"
+    ));
+    let warned = codex_at_home(&home).with_auth_check(AuthCheck::Warn);
+    let mut session = AgentSession::spawn(warned, "true")?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    assert!(matches!(
+        events.first(),
+        Some(AgentEvent::Error {
+            kind: ErrorKind::Auth,
+            ..
+        })
+    ));
+    let skipped = codex_at_home(&home).with_auth_check(AuthCheck::Skip);
+    let mut session = AgentSession::spawn(skipped, "true")?;
+    let events: Vec<AgentEvent> = session.events()?.collect();
+    assert!(!events.iter().any(|e| matches!(e, AgentEvent::Error { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_binary_is_resolved_once_per_session() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let root = common::fake_bin_dir().join("resolve");
+    let (early, late) = (root.join("early"), root.join("late"));
+    let _ = std::fs::create_dir_all(&early);
+    let _ = std::fs::write(early.join("codex"), "not executable");
+    common::install_fake_cli_at(&late.join("codex"));
+    let config = AgentConfig::new(AgentKind::Codex).with_env(
+        "PATH",
+        format!("{}:{}:/usr/bin:/bin", early.display(), late.display()),
+    );
+    let print_program = format!(
+        r#"printf '%s\n' "$(printf '{}' "$0")""#,
+        reply_line(&AgentKind::Codex, "%s")
+    );
+    let started = r#"printf '%s\n' '{"type":"thread.started","thread_id":"t-1"}'"#;
+    let mut session = AgentSession::spawn(config, &format!("{started}; {print_program}"))?;
+    let pinned = late.join("codex").display().to_string();
+    assert_eq!(texts(&mut session)?, std::slice::from_ref(&pinned));
+    common::install_fake_cli_at(&early.join("codex"));
+    session.send_input(&print_program)?;
+    assert_eq!(texts(&mut session)?, [pinned]);
+    Ok(())
+}
+
+#[test]
+fn test_envelopes_order_stdout_and_stderr() -> agent_cli_runner::Result<()> {
+    common::install_fake_clis();
+    let prompt = format!(
+        "printf '%s\\n' '{}'; sleep 0.1; echo 'warning: slow' >&2; sleep 0.1; printf '%s\\n' '{}'",
+        reply_line(&AgentKind::Codex, "one"),
+        reply_line(&AgentKind::Codex, "two")
+    );
+    let mut session = AgentSession::spawn(AgentConfig::new(AgentKind::Codex), &prompt)?;
+    let envelopes: Vec<EventEnvelope> = session.envelopes()?.collect();
+    let seqs: Vec<u64> = envelopes.iter().map(|envelope| envelope.seq).collect();
+    assert_eq!(seqs, [0, 1, 2, 3]);
+    let sources: Vec<EventSource> = envelopes.iter().map(|envelope| envelope.source).collect();
+    assert_eq!(
+        sources,
+        [
+            EventSource::Stdout,
+            EventSource::Stderr,
+            EventSource::Stdout,
+            EventSource::Runner
+        ]
+    );
+    assert!(envelopes
+        .windows(2)
+        .all(|pair| pair[0].received_at <= pair[1].received_at));
+    assert!(matches!(
+        envelopes.last().map(|envelope| &envelope.event),
+        Some(AgentEvent::SessionCompleted { .. })
+    ));
+    assert!(session.events().is_err());
+    Ok(())
+}